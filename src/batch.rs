@@ -0,0 +1,438 @@
+use creature::{self, Creature};
+use physics::{self, Simulation, Environment, Behaviour};
+use physics::{GRAVITY, RESISTANCE, BEHAVIOUR_SAMPLES};
+use std::cmp;
+use std::f32;
+use population::Population;
+use rayon::prelude::*;
+
+/// Number of creatures packed into each batch when a population is split up
+/// to be simulated across multiple threads
+pub const BATCH_SIZE: usize = 64;
+
+/// Many creatures packed into contiguous arrays, so a single pass over each
+/// array simulates every creature in the batch at once. Nodes and muscles
+/// from every creature sit side by side, and muscles refer to nodes by
+/// their index in the whole batch rather than in their own creature.
+pub struct Batch {
+	// Node properties
+	pub x: Vec<f32>,
+	pub y: Vec<f32>,
+	pub vx: Vec<f32>,
+	pub vy: Vec<f32>,
+	pub friction: Vec<f32>,
+	pub node_owner: Vec<usize>,
+
+	// Muscle properties
+	pub node_a: Vec<usize>,
+	pub node_b: Vec<usize>,
+	pub strength: Vec<f32>,
+	pub owner: Vec<usize>,
+
+	// Every muscle's target length for each frame of its cycle is worked out
+	// in advance, and each muscle keeps track of where it is in its own cycle
+	pub targets: Vec<f32>,
+	pub target_start: Vec<usize>,
+	pub period: Vec<u32>,
+	pub cycle: Vec<u32>,
+
+	// The first node, and number of nodes, belonging to each creature, the
+	// total force used by each creature's muscles, and the number of frames
+	// its nodes have spent touching the ground
+	pub creatures: Vec<(usize, usize)>,
+	pub energy: Vec<f32>,
+	pub contact: Vec<u32>
+}
+
+impl Batch {
+	/// Packs the given creatures into a batch, starting from their current
+	/// positions and velocities on the first frame of the simulation
+	pub fn new<'a, I>(creatures: I) -> Batch
+	    where I: Iterator<Item = &'a Creature> {
+		let mut batch = Batch {
+			x: Vec::new(),
+			y: Vec::new(),
+			vx: Vec::new(),
+			vy: Vec::new(),
+			friction: Vec::new(),
+			node_owner: Vec::new(),
+			node_a: Vec::new(),
+			node_b: Vec::new(),
+			strength: Vec::new(),
+			owner: Vec::new(),
+			targets: Vec::new(),
+			target_start: Vec::new(),
+			period: Vec::new(),
+			cycle: Vec::new(),
+			creatures: Vec::new(),
+			energy: Vec::new(),
+			contact: Vec::new()
+		};
+
+		for creature in creatures {
+			let offset = batch.x.len();
+
+			for node in &creature.nodes {
+				batch.x.push(node.x);
+				batch.y.push(node.y);
+				batch.vx.push(node.vx);
+				batch.vy.push(node.vy);
+				batch.friction.push(node.friction);
+				batch.node_owner.push(batch.creatures.len());
+			}
+
+			for muscle in &creature.muscles {
+				batch.node_a.push(offset + muscle.nodes.0);
+				batch.node_b.push(offset + muscle.nodes.1);
+				batch.strength.push(muscle.strength);
+				batch.owner.push(batch.creatures.len());
+
+				let period = muscle.time_extended + muscle.time_contracted;
+				batch.target_start.push(batch.targets.len());
+				batch.period.push(period);
+				batch.cycle.push(muscle.phase % period);
+
+				for timer in 0 .. period {
+					batch.targets.push(physics::lerp(
+						muscle.len_max,
+						muscle.len_min,
+						physics::contraction(
+							muscle.waveform,
+							muscle.time_extended,
+							muscle.time_contracted,
+							0,
+							timer
+						)
+					));
+				}
+			}
+
+			batch.creatures.push((offset, creature.nodes.len()));
+			batch.energy.push(0.0);
+			batch.contact.push(0);
+		}
+
+		batch
+	}
+
+	/// Runs the next frame of the simulation for every creature in the batch
+	pub fn step(&mut self) {
+		// Apply every muscle's force to its nodes' velocities. Rather than
+		// working out the angle between the two nodes, the vector between
+		// them is normalised to give the direction of the force.
+		for idx in 0 .. self.node_a.len() {
+			let a = self.node_a[idx];
+			let b = self.node_b[idx];
+
+			let target = self.targets[self.target_start[idx] +
+			                          self.cycle[idx] as usize];
+
+			self.cycle[idx] += 1;
+			if self.cycle[idx] >= self.period[idx] {
+				self.cycle[idx] = 0;
+			}
+
+			let dx = self.x[a] - self.x[b];
+			let dy = self.y[a] - self.y[b];
+			let distance = (dx * dx + dy * dy).sqrt();
+
+			// Two nodes in the same place push along the X axis, the same as
+			// the angle given by atan2(0, 0)
+			let (nx, ny) = if distance > 0.0 {
+				(dx / distance, dy / distance)
+			} else {
+				(1.0, 0.0)
+			};
+
+			let force = (1.0 - (distance / target)).max(-0.4).min(0.4) *
+			            self.strength[idx];
+
+			self.energy[self.owner[idx]] += force.abs();
+
+			self.vx[a] += nx * force;
+			self.vy[a] += ny * force;
+			self.vx[b] -= nx * force;
+			self.vy[b] -= ny * force;
+		}
+
+		// Then move every node, including gravity and the floor
+		for idx in 0 .. self.x.len() {
+			self.vy[idx] += GRAVITY;
+
+			self.vy[idx] *= RESISTANCE;
+			self.vx[idx] *= RESISTANCE;
+
+			self.x[idx] += self.vx[idx];
+			self.y[idx] += self.vy[idx];
+
+			if self.y[idx] >= creature::BOUNDS_NODE_Y.end {
+				let friction = self.friction[idx];
+				self.contact[self.node_owner[idx]] += 1;
+				self.y[idx] = creature::BOUNDS_NODE_Y.end;
+				self.vy[idx] = 0.0;
+				self.x[idx] -= self.vx[idx] * friction;
+				if self.vx[idx] > 0.0 {
+					self.vx[idx] = (self.vx[idx] - friction).max(0.0);
+				} else {
+					self.vx[idx] = (self.vx[idx] + friction).min(0.0);
+				}
+			}
+		}
+	}
+
+	/// Returns the centre of a creature in the batch, the average position
+	/// of its nodes
+	pub fn centre(&self, idx: usize) -> (f32, f32) {
+		let (start, len) = self.creatures[idx];
+		let (mut x, mut y) = (0.0, 0.0);
+
+		for node in start .. start + len {
+			x += self.x[node];
+			y += self.y[node];
+		}
+
+		(x / len as f32, y / len as f32)
+	}
+
+	/// Returns how far above the floor a creature's lowest node is, the same
+	/// as `Creature::clearance` on a flat floor
+	pub fn clearance(&self, idx: usize) -> f32 {
+		let (start, len) = self.creatures[idx];
+		self.y[start .. start + len].iter().fold(f32::MAX, |gap, y| {
+			gap.min(creature::BOUNDS_NODE_Y.end - y)
+		})
+	}
+
+	/// Returns the fitness of a creature in the batch, calculated the same
+	/// way as `Creature::fitness`
+	pub fn fitness(&self, idx: usize) -> f32 {
+		self.centre(idx).0 - (creature::BOUNDS_NODE_X.end / 2.0)
+	}
+}
+
+/// Simulates every creature in the slice as a single batch. Creatures driven
+/// by a neural controller cannot be packed into a batch, so are simulated on
+/// their own instead, as is every creature when the simulation isn't
+/// `batchable`.
+pub fn simulate_creatures(
+	creatures: &mut [Creature],
+	simulation: &Simulation
+) {
+	if !batchable(simulation) {
+		for creature in creatures {
+			physics::full_simulation_creature(creature, simulation);
+		}
+		return;
+	}
+
+	let (controlled, mut plain): (Vec<&mut Creature>, Vec<&mut Creature>) =
+		creatures.iter_mut().partition(|creature| creature.has_controller());
+
+	for creature in controlled {
+		physics::full_simulation_creature(creature, simulation);
+	}
+
+	for creature in &mut plain {
+		physics::start_position(creature, simulation);
+	}
+
+	let mut batch = Batch::new(plain.iter().map(|creature| &**creature));
+	let centres = (0 .. plain.len())
+		.map(|idx| batch.centre(idx))
+		.collect::<Vec<(f32, f32)>>();
+	let interval = cmp::max(simulation.length / BEHAVIOUR_SAMPLES as u32, 1);
+	let mut traces = vec![Vec::with_capacity(BEHAVIOUR_SAMPLES); plain.len()];
+	let mut heights: Vec<f32> = vec![0.0; plain.len()];
+	let mut landed = vec![false; plain.len()];
+
+	for step in 0 .. simulation.length {
+		batch.step();
+
+		for idx in 0 .. plain.len() {
+			let clearance = batch.clearance(idx);
+			landed[idx] = landed[idx] || clearance <= 0.0;
+			if landed[idx] {
+				heights[idx] = heights[idx].max(clearance);
+			}
+		}
+
+		if (step + 1) % interval == 0 {
+			for idx in 0 .. plain.len() {
+				if traces[idx].len() < BEHAVIOUR_SAMPLES - 1 {
+					let (x, y) = batch.centre(idx);
+					traces[idx].push((x - centres[idx].0, y - centres[idx].1));
+				}
+			}
+		}
+	}
+
+	for (idx, creature) in plain.iter_mut().enumerate() {
+		let mut trace = traces[idx].clone();
+		let (x, y) = batch.centre(idx);
+		while trace.len() < BEHAVIOUR_SAMPLES {
+			trace.push((x - centres[idx].0, y - centres[idx].1));
+		}
+
+		creature.fitness = batch.fitness(idx);
+		creature.energy = batch.energy[idx];
+		creature.fitness_stats = None;
+		creature.behaviour = Behaviour {
+			trace: trace,
+			ground_contact: batch.contact[idx] as f32 /
+				(simulation.length as usize * creature.nodes.len()) as f32,
+			height: heights[idx]
+		};
+		creature.reset_position();
+	}
+}
+
+/// Simulates an entire population in batches spread across every thread,
+/// giving the same fitness values as `physics::full_simulation_population`.
+/// Anything beyond muscles, gravity and a flat floor needs every creature
+/// checked on its own, so those simulations are run one creature at a time
+/// instead.
+pub fn simulate_population(
+	population: &mut Population,
+	simulation: &Simulation
+) {
+	if !batchable(simulation) {
+		return physics::full_simulation_population(population, simulation);
+	}
+
+	population.creatures
+	.par_chunks_mut(BATCH_SIZE)
+	.for_each(|creatures| simulate_creatures(creatures, simulation));
+}
+
+/// Returns whether creatures can be simulated in batches with the given
+/// settings. Early exit rules, external forces, water, slopes, perturbed
+/// simulations and parsimony penalties are not handled by a batch.
+pub fn batchable(simulation: &Simulation) -> bool {
+	simulation.exit_rules.is_empty() &&
+	simulation.forces.is_empty() &&
+	simulation.environment == Environment::Land &&
+	simulation.slope == 0.0 &&
+	simulation.perturbation.is_none() &&
+	simulation.parsimony.is_none()
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use batch::{self, Batch};
+	use physics::{self, Simulation};
+	use population::Population;
+
+	/// Pack a population into a batch and make sure every creature's nodes
+	/// and muscles have been copied across with the correct offsets
+	#[test]
+	fn batch_new() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(10, &mut rng);
+
+		let batch = Batch::new(population.creatures.iter());
+
+		let node_count = population.creatures.iter()
+			.fold(0, |count, creature| count + creature.nodes.len());
+		let muscle_count = population.creatures.iter()
+			.fold(0, |count, creature| count + creature.muscles.len());
+
+		assert_eq!(batch.x.len(), node_count);
+		assert_eq!(batch.node_a.len(), muscle_count);
+		assert_eq!(batch.period.len(), muscle_count);
+		assert_eq!(batch.creatures.len(), 10);
+		assert_eq!(batch.energy.len(), 10);
+
+		for idx in 0 .. 10 {
+			let (start, len) = batch.creatures[idx];
+			assert_eq!(len, population.creatures[idx].nodes.len());
+			assert_approx_eq!(
+				batch.x[start],
+				population.creatures[idx].nodes[0].x
+			);
+			assert_approx_eq!(
+				batch.fitness(idx),
+				population.creatures[idx].fitness()
+			);
+		}
+	}
+
+	/// Make sure the target lengths worked out in advance match the ones used
+	/// when simulating a creature on its own
+	#[test]
+	fn batch_targets() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(10, &mut rng);
+		let mut batch = Batch::new(population.creatures.iter());
+		let muscles = population.creatures.iter()
+			.flat_map(|creature| creature.muscles.iter())
+			.collect::<Vec<_>>();
+
+		for timer in 0 .. 400 {
+			for idx in 0 .. muscles.len() {
+				let target = physics::lerp(
+					muscles[idx].len_max,
+					muscles[idx].len_min,
+					physics::muscle_contraction(muscles[idx], timer)
+				);
+				assert_approx_eq!(
+					batch.targets[batch.target_start[idx] +
+					              batch.cycle[idx] as usize],
+					target
+				);
+			}
+			batch.step();
+		}
+	}
+
+	/// Simulate the same population one creature at a time and in batches,
+	/// making sure every creature ends up with the same fitness, energy and
+	/// jump height
+	#[test]
+	fn simulate_population() {
+		let mut rng = rand::thread_rng();
+		let simulation = Simulation::default();
+		let mut population = Population::new(200, &mut rng);
+		let mut batched = population.clone();
+
+		physics::full_simulation_population(&mut population, &simulation);
+		batch::simulate_population(&mut batched, &simulation);
+
+		for (single, batched) in
+		    population.creatures.iter().zip(&batched.creatures) {
+			assert_approx_eq!(
+				single.fitness,
+				batched.fitness,
+				single.fitness.abs().max(1.0) * 1e-4
+			);
+			assert_approx_eq!(
+				single.energy,
+				batched.energy,
+				single.energy.max(1.0) * 1e-4
+			);
+			assert_approx_eq!(
+				single.behaviour.height,
+				batched.behaviour.height,
+				single.behaviour.height.max(1.0) * 1e-4
+			);
+		}
+	}
+
+	/// Simulate creatures on a slope, which a batch can't handle, making
+	/// sure they are simulated one at a time instead
+	#[test]
+	fn simulate_creatures_unbatchable() {
+		let mut rng = rand::thread_rng();
+		let simulation = Simulation { slope: 0.2, .. Simulation::default() };
+		let mut population = Population::new(20, &mut rng);
+		let mut batched = population.clone();
+
+		physics::full_simulation_population(&mut population, &simulation);
+		batch::simulate_creatures(&mut batched.creatures, &simulation);
+
+		for (single, batched) in
+		    population.creatures.iter().zip(&batched.creatures) {
+			assert_eq!(single.fitness, batched.fitness);
+		}
+	}
+}
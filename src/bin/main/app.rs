@@ -7,10 +7,17 @@ use std::io::prelude::*;
 use std::fs::File;
 
 use cmp6102::population::Population;
+use cmp6102::creature::Creature;
+use cmp6102::physics::Simulation;
 use cmp6102::optimisationmethods::OptimisationMethod;
-use cmp6102::optimisationmethods::hill_climbing::HillClimbing;
+use cmp6102::optimisationmethods::hill_climbing::{HillClimbing, Strategy};
 use cmp6102::optimisationmethods::genetic_algorithm::GeneticAlgorithm;
 use cmp6102::optimisationmethods::simulated_annealing::SimulatedAnnealing;
+use cmp6102::optimisationmethods::map_elites::{MapElites, Descriptor};
+use cmp6102::optimisationmethods::random_search::RandomSearch;
+use cmp6102::optimisationmethods::differential_evolution::{
+	DifferentialEvolution, Variant
+};
 
 pub struct UIData {
 
@@ -35,12 +42,21 @@ pub struct UIData {
 	pub use_genetic_algorithm: bool,
 	pub use_simulated_annealing: bool,
 	pub use_hill_climbing: bool,
+	pub use_map_elites: bool,
+	pub use_random_search: bool,
+	pub use_differential_evolution: bool,
+	pub use_controllers: bool,
+	pub use_water: bool,
 	pub total_generations: usize,
 
 	pub spectate_method: usize,
 	pub spectate_generation: usize,
 	pub spectate_creature: usize,
 
+	// Cell of the spectated method's archive being watched instead of a
+	// creature from one of its generations
+	pub spectate_elite: Option<usize>,
+
 	pub draw_simulation: bool,
 	pub simulation_frame: u32,
 	pub process_generations: usize, pub process_generations_total: usize,
@@ -106,10 +122,16 @@ contents of print.txt");
 			use_genetic_algorithm: true,
 			use_simulated_annealing: false,
 			use_hill_climbing: false,
+			use_map_elites: false,
+			use_random_search: false,
+			use_differential_evolution: false,
+			use_controllers: false,
+			use_water: false,
 			total_generations: 0,
 			spectate_method: 0,
 			spectate_generation: 0,
 			spectate_creature: 0,
+			spectate_elite: None,
 			draw_simulation: false,
 			simulation_frame: 0,
 			process_generations: 0,
@@ -196,22 +218,28 @@ this application is required.".to_string()
 			let gen_size = self.generation_size;
 			let data = self.optmethods[method].get_data();
 			let mut generation = 0;
-			write!(buffer, "{},Lowest,Q1,Avg.,Q3,Highest\n", data.title)
-				.expect("Could not write data");
+			write!(
+				buffer,
+				"{},Lowest,Q1,Avg.,Q3,Highest,Avg. Nodes,Avg. Muscles\n",
+				data.title
+			).expect("Could not write data");
 			for gen in &data.generations {
 				let min = gen_size - 1;
 				let q1 = (gen_size as f64 * 0.75).round() as usize;
 				let q3 = (gen_size as f64 * 0.25).round() as usize;
 				let max = 0;
+				let (nodes, muscles) = gen.complexity_average();
 				write!(
 					buffer,
-					"{}, {}, {}, {}, {}, {}\n",
+					"{}, {}, {}, {}, {}, {}, {}, {}\n",
 					generation,
 					gen.creatures[min].fitness,
 					gen.creatures[q1].fitness,
 					gen.fitness_average(),
 					gen.creatures[q3].fitness,
-					gen.creatures[max].fitness
+					gen.creatures[max].fitness,
+					nodes,
+					muscles
 				).expect("Could not write data");
 				generation += 1;
 			}
@@ -240,7 +268,10 @@ this application is required.".to_string()
 	pub fn init_tests(&mut self) {
 		if !self.use_genetic_algorithm &&
 		   !self.use_hill_climbing &&
-		   !self.use_simulated_annealing
+		   !self.use_simulated_annealing &&
+		   !self.use_map_elites &&
+		   !self.use_random_search &&
+		   !self.use_differential_evolution
 		{
 			return self.modal_new(
 				"Error".to_string(),
@@ -248,7 +279,11 @@ this application is required.".to_string()
 			);
 		}
 
-		let population = Population::new(self.generation_size, &mut self.rng);
+		let mut population =
+			Population::new(self.generation_size, &mut self.rng);
+		if self.use_controllers {
+			population.controller_add(&mut self.rng);
+		}
 
 		if self.use_genetic_algorithm {
 			self.optmethods.push(
@@ -257,17 +292,47 @@ this application is required.".to_string()
 		}
 
 		if self.use_hill_climbing {
+			self.optmethods.push(HillClimbing::new(
+				population.clone(),
+				self.print,
+				Strategy::SteepestAscent
+			));
+		}
+
+		if self.use_simulated_annealing {
 			self.optmethods.push(
-				HillClimbing::new(population.clone(), self.print)
+				SimulatedAnnealing::new(population.clone(), self.print)
 			);
 		}
 
-		if self.use_simulated_annealing {
+		if self.use_differential_evolution {
+			self.optmethods.push(DifferentialEvolution::new(
+				population.clone(),
+				self.print,
+				Variant::Rand1Bin
+			));
+		}
+
+		if self.use_random_search {
 			self.optmethods.push(
-				SimulatedAnnealing::new(population, self.print)
+				RandomSearch::new(population.clone(), self.print)
 			);
 		}
 
+		if self.use_map_elites {
+			self.optmethods.push(MapElites::new(
+				population,
+				self.print,
+				(Descriptor::Nodes, Descriptor::GroundContact)
+			));
+		}
+
+		if self.use_water {
+			for method in &mut self.optmethods {
+				method.simulation_set(Simulation::water());
+			}
+		}
+
 		self.gui_state = GUIState::Generations;
 		self.spectate_creature = 0;
 		self.draw_simulation = false;
@@ -307,9 +372,27 @@ this application is required.".to_string()
 			    .creatures[self.spectate_creature]
 			    .reset_position();
 		}
+		self.spectate_creature_get().reset_position();
 		self.simulation_frame = 0;
 	}
 
+	/// Returns the creature being watched on the spectate screen, either
+	/// from a generation or from the method's archive of elites
+	pub fn spectate_creature_get(&mut self) -> &mut Creature {
+		let method = &mut self.optmethods[self.spectate_method];
+		match self.spectate_elite {
+			Some(cell) => {
+				method.archive_mut().unwrap().cells[cell].as_mut().unwrap()
+			},
+			None => {
+				method.creature_get(
+					self.spectate_generation,
+					self.spectate_creature
+				)
+			}
+		}
+	}
+
 	/// Destroy all the currently used optimisation methods and settings used
 	/// by the generations screen.
 	pub fn reset_optmethods(&mut self) {
@@ -317,6 +400,7 @@ this application is required.".to_string()
 		self.total_generations = 0;
 		self.spectate_generation = 0;
 		self.spectate_creature = 0;
+		self.spectate_elite = None;
 		self.draw_simulation = false;
 		self.simulation_frame = 0;
 	}
@@ -31,6 +31,11 @@ widget_ids! {
 		new_toggle_ga,
 		new_toggle_sa,
 		new_toggle_hc,
+		new_toggle_me,
+		new_toggle_rs,
+		new_toggle_de,
+		new_toggle_controller,
+		new_toggle_water,
 		new_slider_gensize,
 		new_btn_start,
 		new_btn_back,
@@ -45,46 +50,79 @@ widget_ids! {
 		gen_rect_ga,
 		gen_rect_sa,
 		gen_rect_hc,
+		gen_rect_me,
+		gen_rect_rs,
+		gen_rect_de,
 
 		gen_grid_ga,
 		gen_grid_sa,
 		gen_grid_hc,
+		gen_grid_me,
+		gen_grid_rs,
+		gen_grid_de,
 
 		gen_graph_ga_max,
 		gen_graph_sa_max,
 		gen_graph_hc_max,
+		gen_graph_me_max,
+		gen_graph_rs_max,
+		gen_graph_de_max,
 
 		gen_graph_ga_avg,
 		gen_graph_sa_avg,
 		gen_graph_hc_avg,
+		gen_graph_me_avg,
+		gen_graph_rs_avg,
+		gen_graph_de_avg,
 
 		gen_graph_ga_min,
 		gen_graph_sa_min,
 		gen_graph_hc_min,
+		gen_graph_me_min,
+		gen_graph_rs_min,
+		gen_graph_de_min,
 
 		gen_line_ga,
 		gen_line_sa,
 		gen_line_hc,
+		gen_line_me,
+		gen_line_rs,
+		gen_line_de,
 
 		gen_circle_ga,
 		gen_circle_sa,
 		gen_circle_hc,
+		gen_circle_me,
+		gen_circle_rs,
+		gen_circle_de,
 
 		gen_slider_ga,
 		gen_slider_sa,
 		gen_slider_hc,
+		gen_slider_me,
+		gen_slider_rs,
+		gen_slider_de,
 
 		gen_btn_ga,
 		gen_btn_sa,
 		gen_btn_hc,
+		gen_btn_me,
+		gen_btn_rs,
+		gen_btn_de,
 
 		gen_fittest_ga,
 		gen_fittest_sa,
 		gen_fittest_hc,
+		gen_fittest_me,
+		gen_fittest_rs,
+		gen_fittest_de,
 
 		gen_txt_ga,
 		gen_txt_sa,
 		gen_txt_hc,
+		gen_txt_me,
+		gen_txt_rs,
+		gen_txt_de,
 
 		gen_btn_gen_single,
 		gen_slider_gen_do,
@@ -94,6 +132,7 @@ widget_ids! {
 		// Spectate Single Creature Widgets
 		dc_text,
 		dc_reset,
+		dc_elite,
 		dc_back,
 		dc_physics,
 
@@ -270,6 +309,7 @@ fn menu_new_test (
 		.w_h(canvas_width, canvas_height)
 		.pad(MARGIN)
 		.border(0.0)
+		.scroll_kids_vertically()
 		.set(ids.new_canvas, ui);
 
 	// Canvas Title
@@ -308,6 +348,46 @@ fn menu_new_test (
 			"Use Hill Climbing: Off"
 		};
 
+	let use_me = app.use_map_elites;
+	let use_me_title =
+		if use_me {
+			"Use MAP-Elites: On"
+		} else {
+			"Use MAP-Elites: Off"
+		};
+
+	let use_rs = app.use_random_search;
+	let use_rs_title =
+		if use_rs {
+			"Use Random Search: On"
+		} else {
+			"Use Random Search: Off"
+		};
+
+	let use_de = app.use_differential_evolution;
+	let use_de_title =
+		if use_de {
+			"Use Differential Evolution: On"
+		} else {
+			"Use Differential Evolution: Off"
+		};
+
+	let use_controllers = app.use_controllers;
+	let use_controllers_title =
+		if use_controllers {
+			"Use Neural Controllers: On"
+		} else {
+			"Use Neural Controllers: Off"
+		};
+
+	let use_water = app.use_water;
+	let use_water_title =
+		if use_water {
+			"Environment: Water"
+		} else {
+			"Environment: Land"
+		};
+
 	// First toggle: Genetic Algorithms
 	for use_ga in widget::Toggle::new(use_ga)
 		.label(use_ga_title)
@@ -353,6 +433,81 @@ fn menu_new_test (
 		app.use_hill_climbing = use_hc;
 	}
 
+	// Fourth toggle: MAP-Elites
+	for use_me in widget::Toggle::new(use_me)
+		.label(use_me_title)
+		.label_color(COL_LBL)
+		.label_font_size(20)
+		.color(COL_BTN)
+		.mid_left()
+		.down_from(ids.new_toggle_hc, SPACING)
+		.w_h(canvas_width - (MARGIN * 2.0), 48.0)
+		.border(0.0)
+		.set(ids.new_toggle_me, ui)
+	{
+		app.use_map_elites = use_me;
+	}
+
+	// Fifth toggle: Random Search, as a baseline for the other methods
+	for use_rs in widget::Toggle::new(use_rs)
+		.label(use_rs_title)
+		.label_color(COL_LBL)
+		.label_font_size(20)
+		.color(COL_BTN)
+		.mid_left()
+		.down_from(ids.new_toggle_me, SPACING)
+		.w_h(canvas_width - (MARGIN * 2.0), 48.0)
+		.border(0.0)
+		.set(ids.new_toggle_rs, ui)
+	{
+		app.use_random_search = use_rs;
+	}
+
+	// Sixth toggle: Differential Evolution
+	for use_de in widget::Toggle::new(use_de)
+		.label(use_de_title)
+		.label_color(COL_LBL)
+		.label_font_size(20)
+		.color(COL_BTN)
+		.mid_left()
+		.down_from(ids.new_toggle_rs, SPACING)
+		.w_h(canvas_width - (MARGIN * 2.0), 48.0)
+		.border(0.0)
+		.set(ids.new_toggle_de, ui)
+	{
+		app.use_differential_evolution = use_de;
+	}
+
+	// Give creatures a neural controller to drive their muscles
+	for use_controllers in widget::Toggle::new(use_controllers)
+		.label(use_controllers_title)
+		.label_color(COL_LBL)
+		.label_font_size(20)
+		.color(COL_BTN)
+		.mid_left()
+		.down_from(ids.new_toggle_de, SPACING)
+		.w_h(canvas_width - (MARGIN * 2.0), 48.0)
+		.border(0.0)
+		.set(ids.new_toggle_controller, ui)
+	{
+		app.use_controllers = use_controllers;
+	}
+
+	// Evolve swimming creatures instead of walking ones
+	for use_water in widget::Toggle::new(use_water)
+		.label(use_water_title)
+		.label_color(COL_LBL)
+		.label_font_size(20)
+		.color(COL_BTN)
+		.mid_left()
+		.down_from(ids.new_toggle_controller, SPACING)
+		.w_h(canvas_width - (MARGIN * 2.0), 48.0)
+		.border(0.0)
+		.set(ids.new_toggle_water, ui)
+	{
+		app.use_water = use_water;
+	}
+
 	// Set the size of each generation (100 - 2000)
 	let gensize = app.generation_size as f64;
 	for value in widget::Slider::new(gensize, 100.0, 2000.0)
@@ -361,7 +516,7 @@ fn menu_new_test (
 		.label_font_size(20)
 		.color(COL_BTN)
 		.mid_left()
-		.down_from(ids.new_toggle_hc, SPACING)
+		.down_from(ids.new_toggle_water, SPACING)
 		.w_h(canvas_width - (MARGIN * 2.0), 48.0)
 		.border(0.0)
 		.set(ids.new_slider_gensize, ui)
@@ -640,57 +795,90 @@ time to process.".to_string()
 	let ids_rect = vec![
 		ids.gen_rect_ga,
 		ids.gen_rect_sa,
-		ids.gen_rect_hc
+		ids.gen_rect_hc,
+		ids.gen_rect_me,
+		ids.gen_rect_rs,
+		ids.gen_rect_de
 	];
 	let ids_grid = vec![
 		ids.gen_grid_ga,
 		ids.gen_grid_sa,
-		ids.gen_grid_hc
+		ids.gen_grid_hc,
+		ids.gen_grid_me,
+		ids.gen_grid_rs,
+		ids.gen_grid_de
 	];
 	let ids_slider = vec![
 		ids.gen_slider_ga,
 		ids.gen_slider_sa,
-		ids.gen_slider_hc
+		ids.gen_slider_hc,
+		ids.gen_slider_me,
+		ids.gen_slider_rs,
+		ids.gen_slider_de
 	];
 	let ids_btn = vec![
 		ids.gen_btn_ga,
 		ids.gen_btn_sa,
-		ids.gen_btn_hc
+		ids.gen_btn_hc,
+		ids.gen_btn_me,
+		ids.gen_btn_rs,
+		ids.gen_btn_de
 	];
 	let ids_txt = vec![
 		ids.gen_txt_ga,
 		ids.gen_txt_sa,
-		ids.gen_txt_hc
+		ids.gen_txt_hc,
+		ids.gen_txt_me,
+		ids.gen_txt_rs,
+		ids.gen_txt_de
 	];
 	let ids_graph_max = vec![
 		ids.gen_graph_ga_max,
 		ids.gen_graph_sa_max,
-		ids.gen_graph_hc_max
+		ids.gen_graph_hc_max,
+		ids.gen_graph_me_max,
+		ids.gen_graph_rs_max,
+		ids.gen_graph_de_max
 	];
 	let ids_graph_avg = vec![
 		ids.gen_graph_ga_avg,
 		ids.gen_graph_sa_avg,
-		ids.gen_graph_hc_avg
+		ids.gen_graph_hc_avg,
+		ids.gen_graph_me_avg,
+		ids.gen_graph_rs_avg,
+		ids.gen_graph_de_avg
 	];
 	let ids_graph_min = vec![
 		ids.gen_graph_ga_min,
 		ids.gen_graph_sa_min,
-		ids.gen_graph_hc_min
+		ids.gen_graph_hc_min,
+		ids.gen_graph_me_min,
+		ids.gen_graph_rs_min,
+		ids.gen_graph_de_min
 	];
 	let ids_line = vec![
 		ids.gen_line_ga,
 		ids.gen_line_sa,
-		ids.gen_line_hc
+		ids.gen_line_hc,
+		ids.gen_line_me,
+		ids.gen_line_rs,
+		ids.gen_line_de
 	];
 	let ids_circle = vec![
 		ids.gen_circle_ga,
 		ids.gen_circle_sa,
-		ids.gen_circle_hc
+		ids.gen_circle_hc,
+		ids.gen_circle_me,
+		ids.gen_circle_rs,
+		ids.gen_circle_de
 	];
 	let ids_btn_fittest = vec![
 		ids.gen_fittest_ga,
 		ids.gen_fittest_sa,
-		ids.gen_fittest_hc
+		ids.gen_fittest_hc,
+		ids.gen_fittest_me,
+		ids.gen_fittest_rs,
+		ids.gen_fittest_de
 	];
 
 	// Calculate the height of each section depending on how many methods
@@ -741,6 +929,7 @@ time to process.".to_string()
 			// Switch to the spectator screen
 			app.gui_state = GUIState::Spectate;
 			app.spectate_method = mtd;
+			app.spectate_elite = None;
 			app.draw_simulation = true;
 		}
 
@@ -929,13 +1118,10 @@ fn menu_spectate (
 		.border(0.0)
 		.set(ids.dc_back, ui)
 	{
-		let data = app.optmethods[app.spectate_method].get_data_mut();
-
-		data.generations[app.spectate_generation]
-		    .creatures[app.spectate_creature]
-		    .reset_position();
+		app.spectate_creature_get().reset_position();
 
 		app.gui_state = GUIState::Generations;
+		app.spectate_elite = None;
 		app.draw_simulation = false;
 		app.simulation_frame = 0;
 	}
@@ -954,15 +1140,66 @@ fn menu_spectate (
 		app.reset_simulation();
 	}
 
+	// Cycle through the creatures kept in the method's archive, if it has
+	// one. The first position watches the creature from the generation.
+	let elites = app.optmethods[app.spectate_method]
+		.archive()
+		.map(|archive| archive.elites());
+
+	if let Some(elites) = elites {
+		let selected = app.spectate_elite
+			.and_then(|cell| elites.iter().position(|&elite| elite == cell))
+			.map_or(0, |idx| idx + 1);
+
+		let label = match app.spectate_elite {
+			Some(cell) => {
+				let archive =
+					app.optmethods[app.spectate_method].archive().unwrap();
+				let (x, y) = archive.position(cell);
+				let (first, second) = archive.descriptors;
+				let first_range = first.bin_range(x);
+				let second_range = second.bin_range(y);
+
+				format!(
+					"Elite {} / {}: {} {:.1}-{:.1}, {} {:.1}-{:.1}",
+					selected, elites.len(),
+					first.title(), first_range.start, first_range.end,
+					second.title(), second_range.start, second_range.end
+				)
+			},
+			None => format!("Elites: {} in archive", elites.len())
+		};
+
+		for value in widget::Slider::new(
+			selected as f32, 0.0, elites.len() as f32
+		)
+			.label(&*label)
+			.label_color(COL_LBL)
+			.label_font_size(16)
+			.color(COL_BTN)
+			.right_from(ids.dc_reset, 16.0)
+			.w_h(512.0, 32.0)
+			.border(0.0)
+			.set(ids.dc_elite, ui)
+		{
+			let elite = match value.round() as usize {
+				0 => None,
+				idx => Some(elites[idx - 1])
+			};
+
+			// Put the creature being watched back where it started before
+			// switching to another one
+			if elite != app.spectate_elite {
+				app.spectate_creature_get().reset_position();
+				app.spectate_elite = elite;
+				app.simulation_frame = 0;
+			}
+		}
+	}
+
 	// Print/draw the fitness of the creature to bottom center of the screen
 	widget::Text::new(
-		&*format!("{}",
-			app.optmethods[app.spectate_method]
-			   .get_data()
-			   .generations[app.spectate_generation]
-			   .creatures[app.spectate_creature]
-			   .fitness() as i32
-		)
+		&*format!("{}", app.spectate_creature_get().fitness() as i32)
 	)
 	.font_size(32)
 	.font_id(fonts.bold)
@@ -18,7 +18,7 @@ use piston_window::texture::UpdateTexture;
 use conrod::backend::piston::event::convert;
 use gui::GUIState;
 use app::UIData;
-use cmp6102::physics;
+use cmp6102::physics::{self, Environment};
 use cmp6102::creature::{self, Creature};
 
 fn main() {
@@ -135,12 +135,22 @@ fn main() {
 
 			match app.gui_state {
 			GUIState::Spectate => {
-				let mut creature =
-					app.optmethods[app.spectate_method]
-					.creature_get(
-						app.spectate_generation,
-						app.spectate_creature
-					);
+				let simulation = app.optmethods[app.spectate_method]
+					.get_data().simulation.clone();
+				let mut creature = match app.spectate_elite {
+					Some(cell) => {
+						app.optmethods[app.spectate_method]
+						.archive_mut().unwrap()
+						.cells[cell].as_mut().unwrap()
+					},
+					None => {
+						app.optmethods[app.spectate_method]
+						.creature_get(
+							app.spectate_generation,
+							app.spectate_creature
+						)
+					}
+				};
 				let x = creature.fitness() as f64;
 				let y = 84.0;
 
@@ -167,16 +177,31 @@ fn main() {
 					1.0, context, graphics
 				);
 
-				rectangle(
-					[0.15, 0.9, 0.1, 1.0],
-					[0.0, app.height as f64 - y,
-					 app.width as f64, y],
-					context.transform, graphics
-				);
+				// There's no floor in the water, so tint the whole window
+				// instead of drawing the ground
+				if simulation.environment == Environment::Water {
+					rectangle(
+						[0.1, 0.4, 0.9, 0.25],
+						[0.0, 0.0,
+						 app.width as f64, app.height as f64],
+						context.transform, graphics
+					);
+				} else {
+					rectangle(
+						[0.15, 0.9, 0.1, 1.0],
+						[0.0, app.height as f64 - y,
+						 app.width as f64, y],
+						context.transform, graphics
+					);
+				}
 				if app.draw_simulation {
+					if app.simulation_frame == 0 {
+						physics::start_position(&mut creature, &simulation);
+					}
 					physics::simulation_step(
 						app.simulation_frame,
-						&mut creature
+						&mut creature,
+						&simulation
 					);
 					app.simulation_frame += 1;
 				}
@@ -1,13 +1,33 @@
 extern crate cmp6102;
 extern crate rand;
 extern crate clap;
+extern crate time;
 
 use cmp6102::population::Population;
-use cmp6102::optimisationmethods::OptimisationMethod;
-use cmp6102::optimisationmethods::hill_climbing::HillClimbing;
+use cmp6102::physics::{self, Simulation, ForceField, Environment};
+use cmp6102::physics::{Perturbation, Parsimony};
+use cmp6102::batch;
+use cmp6102::optimisationmethods::{OptimisationMethod, OpMethodData};
+use cmp6102::optimisationmethods::hill_climbing::{self, HillClimbing};
 use cmp6102::optimisationmethods::genetic_algorithm::GeneticAlgorithm;
 use cmp6102::optimisationmethods::simulated_annealing::SimulatedAnnealing;
+use cmp6102::optimisationmethods::nsga2::Nsga2;
+use cmp6102::optimisationmethods::novelty_search::NoveltySearch;
+use cmp6102::optimisationmethods::map_elites::{MapElites, Descriptor};
+use cmp6102::optimisationmethods::random_search::RandomSearch;
+use cmp6102::optimisationmethods::evolution_strategies::{self, EvolutionStrategies};
+use cmp6102::optimisationmethods::cma_es::CmaEs;
+use cmp6102::optimisationmethods::particle_swarm::{self, ParticleSwarm};
+use cmp6102::optimisationmethods::differential_evolution::{self, DifferentialEvolution};
+use cmp6102::optimisationmethods::tabu_search::{self, TabuSearch};
+use cmp6102::optimisationmethods::memetic::{self, Memetic};
+use cmp6102::optimisationmethods::island_model::{self, IslandModel};
+use cmp6102::optimisationmethods::speciation::{self, Speciation};
+use cmp6102::optimisationmethods::parallel_tempering::{self, ParallelTempering};
+use cmp6102::creature::Objective;
 use clap::{Arg, App};
+use std::io::prelude::*;
+use std::fs::File;
 
 fn main () {
 
@@ -33,6 +53,65 @@ fn main () {
 						 .value_name("size")
 						 .help("Sets the number of samples to collect")
 						 .takes_value(true))
+					.arg(Arg::with_name("budget")
+						 .long("budget")
+						 .value_name("evaluations")
+						 .help("Runs each method until it has simulated this many creatures, instead of for a number of generations")
+						 .takes_value(true))
+					.arg(Arg::with_name("controllers")
+						 .short("c")
+						 .long("controllers")
+						 .help("Gives every creature a neural controller to drive its muscles")
+						 .takes_value(false))
+					.arg(Arg::with_name("early_exit")
+						 .short("e")
+						 .long("early_exit")
+						 .help("Ends each creature's simulation early once it stops moving, leaves the world or explodes")
+						 .takes_value(false))
+					.arg(Arg::with_name("ground_start")
+						 .long("ground_start")
+						 .help("Moves each creature down onto the ground before it is simulated, rather than letting it fall")
+						 .takes_value(false))
+					.arg(Arg::with_name("settle")
+						 .long("settle")
+						 .value_name("frames")
+						 .help("Lets each creature settle with its muscles relaxed for this many frames before it is simulated")
+						 .takes_value(true))
+					.arg(Arg::with_name("evaluations")
+						 .short("k")
+						 .long("evaluations")
+						 .value_name("count")
+						 .help("Simulates each creature this many times with small random changes, using its average fitness")
+						 .takes_value(true))
+					.arg(Arg::with_name("parsimony")
+						 .long("parsimony")
+						 .value_name("weight")
+						 .help("Takes this much fitness away for every node and muscle a creature has")
+						 .takes_value(true))
+					.arg(Arg::with_name("water")
+						 .long("water")
+						 .help("Evolves swimming creatures in water, without gravity or a floor")
+						 .takes_value(false))
+					.arg(Arg::with_name("wind")
+						 .short("w")
+						 .long("wind")
+						 .value_name("strength")
+						 .help("Evolves creatures against a headwind of the given strength")
+						 .takes_value(true))
+					.arg(Arg::with_name("robustness")
+						 .short("r")
+						 .long("robustness")
+						 .value_name("strength")
+						 .help("Tests each method's fittest creature against a headwind and gusts of the given strength")
+						 .takes_value(true))
+					.arg(Arg::with_name("benchmark_early_exit")
+						 .long("benchmark_early_exit")
+						 .help("Measures how much faster a population is simulated when ending simulations early, then exits")
+						 .takes_value(false))
+					.arg(Arg::with_name("benchmark_batch")
+						 .long("benchmark_batch")
+						 .help("Measures how much faster a population is simulated in batches than one creature at a time, then exits")
+						 .takes_value(false))
 					.arg(Arg::with_name("genetic_algorithm")
 						 .short("G")
 						 .long("genetic_algorithm_skip")
@@ -48,6 +127,185 @@ fn main () {
 						 .long("hill_climbing_skip")
 						 .help("Excludes hill climbing from the test")
 						 .takes_value(false))
+					.arg(Arg::with_name("hc_strategy")
+						 .long("hc_strategy")
+						 .value_name("steepest|first")
+						 .help("Whether hill climbing moves to the fittest of its neighbours (steepest) or the first which improves (first)")
+						 .takes_value(true))
+					.arg(Arg::with_name("hc_neighbours")
+						 .long("hc_neighbours")
+						 .value_name("count")
+						 .help("The most neighbours hill climbing simulates for each creature every generation")
+						 .takes_value(true))
+					.arg(Arg::with_name("hc_restart")
+						 .long("hc_restart")
+						 .value_name("generations")
+						 .help("Restarts a hill climbing creature from a new random one after this many generations without improving")
+						 .takes_value(true))
+					.arg(Arg::with_name("hc_adaptive")
+						 .long("hc_adaptive")
+						 .help("Grows hill climbing's step size after improving and shrinks it otherwise")
+						 .takes_value(false))
+					.arg(Arg::with_name("differential_evolution")
+						 .short("D")
+						 .long("differential_evolution")
+						 .help("Includes differential evolution in the test")
+						 .takes_value(false))
+					.arg(Arg::with_name("de_variant")
+						 .long("de_variant")
+						 .value_name("rand|best")
+						 .help("Whether differential evolution builds trial creatures from a random creature (rand/1/bin) or the fittest (best/1/bin)")
+						 .takes_value(true))
+					.arg(Arg::with_name("particle_swarm")
+						 .short("P")
+						 .long("particle_swarm")
+						 .help("Includes particle swarm optimisation in the test")
+						 .takes_value(false))
+					.arg(Arg::with_name("inertia")
+						 .long("inertia")
+						 .value_name("weight")
+						 .help("How much of its velocity each particle keeps every generation")
+						 .takes_value(true))
+					.arg(Arg::with_name("acceleration")
+						 .long("acceleration")
+						 .value_name("cognitive,social")
+						 .help("How strongly particles are pulled towards their own best and their neighbours' best")
+						 .takes_value(true))
+					.arg(Arg::with_name("neighbourhood")
+						 .long("neighbourhood")
+						 .value_name("radius")
+						 .help("Particles follow the best of this many neighbours either side of them, rather than the whole swarm")
+						 .takes_value(true))
+					.arg(Arg::with_name("parallel_tempering")
+						 .short("R")
+						 .long("parallel_tempering")
+						 .help("Includes parallel tempering, annealing replicas at different temperatures which swap creatures, in the test")
+						 .takes_value(false))
+					.arg(Arg::with_name("temperatures")
+						 .long("temperatures")
+						 .value_name("list")
+						 .help("Comma separated temperatures for parallel tempering's replicas, from coldest to hottest")
+						 .takes_value(true))
+					.arg(Arg::with_name("speciation")
+						 .short("S")
+						 .long("speciation")
+						 .help("Includes the genetic algorithm with speciation, sharing fitness between creatures with similar body plans, in the test")
+						 .takes_value(false))
+					.arg(Arg::with_name("compatibility")
+						 .long("compatibility")
+						 .value_name("distance")
+						 .help("The furthest a creature can be from a species' representative and still belong to it")
+						 .takes_value(true))
+					.arg(Arg::with_name("island_model")
+						 .short("I")
+						 .long("island_model")
+						 .help("Includes the island model, running the genetic algorithm on separate islands with migration, in the test")
+						 .takes_value(false))
+					.arg(Arg::with_name("islands")
+						 .long("islands")
+						 .value_name("count")
+						 .help("The number of islands the island model splits the population into")
+						 .takes_value(true))
+					.arg(Arg::with_name("topology")
+						 .long("topology")
+						 .value_name("ring|full")
+						 .help("Whether island model creatures migrate to the next island (ring) or every other island (full)")
+						 .takes_value(true))
+					.arg(Arg::with_name("migration")
+						 .long("migration")
+						 .value_name("generations")
+						 .help("The number of generations between each migration in the island model, or 0 to never migrate")
+						 .takes_value(true))
+					.arg(Arg::with_name("memetic")
+						 .short("L")
+						 .long("memetic")
+						 .help("Includes the memetic algorithm, refining the genetic algorithm's children with hill climbing, in the test")
+						 .takes_value(false))
+					.arg(Arg::with_name("learning")
+						 .long("learning")
+						 .value_name("lamarckian|baldwinian")
+						 .help("Whether the memetic algorithm's children keep their refined genome or only its fitness")
+						 .takes_value(true))
+					.arg(Arg::with_name("refine")
+						 .long("refine")
+						 .value_name("count")
+						 .help("Only refines this many of the memetic algorithm's fittest children, rather than all of them")
+						 .takes_value(true))
+					.arg(Arg::with_name("tabu_search")
+						 .short("T")
+						 .long("tabu_search")
+						 .help("Includes tabu search, which walks to the fittest neighbour it hasn't recently visited, in the test")
+						 .takes_value(false))
+					.arg(Arg::with_name("tabu_memory")
+						 .long("tabu_memory")
+						 .value_name("genomes|moves")
+						 .help("Whether tabu search remembers the regions of genomes it has visited or the nodes and muscles it has changed")
+						 .takes_value(true))
+					.arg(Arg::with_name("tabu_tenure")
+						 .long("tabu_tenure")
+						 .value_name("moves")
+						 .help("The number of moves something stays on tabu search's tabu list for")
+						 .takes_value(true))
+					.arg(Arg::with_name("random_search")
+						 .short("B")
+						 .long("random_search")
+						 .help("Includes random search, a baseline of brand new random creatures every generation, in the test")
+						 .takes_value(false))
+					.arg(Arg::with_name("evolution_strategies")
+						 .short("E")
+						 .long("evolution_strategies")
+						 .help("Includes evolution strategies, where every creature adapts its own mutation step size, in the test")
+						 .takes_value(false))
+					.arg(Arg::with_name("es_selection")
+						 .long("es_selection")
+						 .value_name("plus|comma")
+						 .help("Whether evolution strategies' parents compete with their children (plus) or are always replaced (comma)")
+						 .takes_value(true))
+					.arg(Arg::with_name("cma_es")
+						 .short("C")
+						 .long("cma_es")
+						 .help("Includes CMA-ES, optimising the genes of the fittest starting creature's body plan, in the test")
+						 .takes_value(false))
+					.arg(Arg::with_name("nsga2")
+						 .short("N")
+						 .long("nsga2")
+						 .help("Includes NSGA-II, optimising several objectives at once, in the test")
+						 .takes_value(false))
+					.arg(Arg::with_name("novelty")
+						 .short("V")
+						 .long("novelty")
+						 .help("Includes novelty search, rewarding creatures for moving differently, in the test")
+						 .takes_value(false))
+					.arg(Arg::with_name("novelty_blend")
+						 .long("novelty_blend")
+						 .value_name("weight")
+						 .help("How much novelty search scores creatures by fitness rather than novelty, from 0 to 1")
+						 .takes_value(true))
+					.arg(Arg::with_name("map_elites")
+						 .short("M")
+						 .long("map_elites")
+						 .help("Includes MAP-Elites, keeping the fittest creature for each combination of two descriptors, in the test")
+						 .takes_value(false))
+					.arg(Arg::with_name("descriptors")
+						 .long("descriptors")
+						 .value_name("list")
+						 .help("Two comma separated descriptors for MAP-Elites' grid: nodes, contact, jump, distance")
+						 .takes_value(true))
+					.arg(Arg::with_name("archive")
+						 .long("archive")
+						 .value_name("file")
+						 .help("Exports the MAP-Elites archive from every sample to a CSV file, one cell per line")
+						 .takes_value(true))
+					.arg(Arg::with_name("objectives")
+						 .long("objectives")
+						 .value_name("list")
+						 .help("Comma separated objectives for NSGA-II to optimise: distance, energy, simplicity")
+						 .takes_value(true))
+					.arg(Arg::with_name("pareto")
+						 .long("pareto")
+						 .value_name("file")
+						 .help("Exports each generation's Pareto front from every sample to a CSV file, and shows the final front")
+						 .takes_value(true))
 					.get_matches();
 
 	let mut rng = rand::thread_rng();
@@ -55,6 +313,51 @@ fn main () {
 	let gen_count = matches.value_of("generations").unwrap_or("200").parse::<usize>().unwrap();
 	let pop_size = matches.value_of("population").unwrap_or("1000").parse::<usize>().unwrap();
 	let sample_size = matches.value_of("samples").unwrap_or("100").parse::<usize>().unwrap();
+	let budget = matches.value_of("budget").map(|evaluations| evaluations.parse::<usize>().unwrap());
+
+	if matches.is_present("benchmark_early_exit") {
+		return benchmark_early_exit(pop_size, &mut rng);
+	}
+
+	if matches.is_present("benchmark_batch") {
+		return benchmark_batch(pop_size, &mut rng);
+	}
+
+	let mut simulation = if matches.is_present("early_exit") {
+		Simulation::early_exit()
+	} else {
+		Simulation::default()
+	};
+
+	if matches.is_present("ground_start") {
+		simulation.ground_start = true;
+	}
+
+	if let Some(frames) = matches.value_of("settle") {
+		simulation.settle_frames = frames.parse::<u32>().unwrap();
+	}
+
+	if let Some(evaluations) = matches.value_of("evaluations") {
+		let evaluations = evaluations.parse::<u32>().unwrap();
+		simulation.perturbation = Some(Perturbation::new(evaluations));
+	}
+
+	if let Some(weight) = matches.value_of("parsimony") {
+		let weight = weight.parse::<f32>().unwrap();
+		simulation.parsimony = Some(Parsimony::new(weight));
+	}
+
+	if matches.is_present("water") {
+		simulation.environment = Environment::Water;
+	}
+
+	if let Some(strength) = matches.value_of("wind") {
+		let strength = strength.parse::<f32>().unwrap();
+		simulation.forces = Simulation::headwind(strength).forces;
+	}
+
+	let robustness = matches.value_of("robustness")
+		.map(|strength| strength.parse::<f32>().unwrap());
 
 	let mut optmethods = Vec::new();
 
@@ -76,6 +379,175 @@ fn main () {
 		optmethods.push("SA");
 	}
 
+	if matches.is_present("differential_evolution") {
+		optmethods.push("DE");
+	}
+
+	if matches.is_present("particle_swarm") {
+		optmethods.push("PSO");
+	}
+
+	if matches.is_present("parallel_tempering") {
+		optmethods.push("PT");
+	}
+
+	if matches.is_present("speciation") {
+		optmethods.push("SP");
+	}
+
+	if matches.is_present("island_model") {
+		optmethods.push("IM");
+	}
+
+	if matches.is_present("memetic") {
+		optmethods.push("MA");
+	}
+
+	if matches.is_present("tabu_search") {
+		optmethods.push("TS");
+	}
+
+	if matches.is_present("random_search") {
+		optmethods.push("RS");
+	}
+
+	if matches.is_present("evolution_strategies") {
+		optmethods.push("ES");
+	}
+
+	if matches.is_present("cma_es") {
+		optmethods.push("CMA");
+	}
+
+	if matches.is_present("nsga2") {
+		optmethods.push("NSGA");
+	}
+
+	if matches.is_present("novelty") {
+		optmethods.push("NS");
+	}
+
+	if matches.is_present("map_elites") {
+		optmethods.push("ME");
+	}
+
+	let novelty_blend = matches.value_of("novelty_blend")
+		.unwrap_or("0")
+		.parse::<f32>()
+		.unwrap();
+
+	let objectives = matches.value_of("objectives")
+		.unwrap_or("distance,energy")
+		.split(',')
+		.map(|objective| match objective.trim() {
+			"distance" => Objective::Distance,
+			"energy" => Objective::Energy,
+			"simplicity" => Objective::Simplicity,
+			_ => panic!("Unknown objective: {}", objective)
+		})
+		.collect::<Vec<Objective>>();
+
+	let es_selection = match matches.value_of("es_selection").unwrap_or("plus") {
+		"plus" => evolution_strategies::Selection::Plus,
+		"comma" => evolution_strategies::Selection::Comma,
+		selection => panic!("Unknown selection: {}", selection)
+	};
+
+	let hc_strategy = match matches.value_of("hc_strategy").unwrap_or("steepest") {
+		"steepest" => hill_climbing::Strategy::SteepestAscent,
+		"first" => hill_climbing::Strategy::FirstImprovement,
+		strategy => panic!("Unknown strategy: {}", strategy)
+	};
+
+	let hc_neighbours = matches.value_of("hc_neighbours")
+		.map_or(hill_climbing::CLIMB_ATTEMPTS, |neighbours| neighbours.parse::<usize>().unwrap());
+
+	let hc_restart = matches.value_of("hc_restart")
+		.map(|generations| generations.parse::<usize>().unwrap());
+
+	let de_variant = match matches.value_of("de_variant").unwrap_or("rand") {
+		"rand" => differential_evolution::Variant::Rand1Bin,
+		"best" => differential_evolution::Variant::Best1Bin,
+		variant => panic!("Unknown variant: {}", variant)
+	};
+
+	let temperatures = matches.value_of("temperatures")
+		.map_or(parallel_tempering::temperatures(parallel_tempering::REPLICAS), |temperatures| {
+			temperatures.split(',')
+				.map(|temperature| temperature.trim().parse::<f32>().unwrap())
+				.collect::<Vec<f32>>()
+		});
+
+	if !parallel_tempering::temperatures_valid(&temperatures) {
+		panic!("Temperatures must be positive and ascending: {:?}", temperatures);
+	}
+
+	let compatibility = matches.value_of("compatibility")
+		.map_or(speciation::COMPATIBILITY_THRESHOLD, |distance| distance.parse::<f32>().unwrap());
+
+	let islands = matches.value_of("islands")
+		.map_or(island_model::ISLANDS, |count| count.parse::<usize>().unwrap());
+
+	let topology = match matches.value_of("topology").unwrap_or("ring") {
+		"ring" => island_model::Topology::Ring,
+		"full" => island_model::Topology::FullyConnected,
+		topology => panic!("Unknown topology: {}", topology)
+	};
+
+	let migration = matches.value_of("migration")
+		.map_or(island_model::MIGRATION_INTERVAL, |interval| interval.parse::<usize>().unwrap());
+
+	let learning = match matches.value_of("learning").unwrap_or("lamarckian") {
+		"lamarckian" => memetic::Learning::Lamarckian,
+		"baldwinian" => memetic::Learning::Baldwinian,
+		learning => panic!("Unknown learning: {}", learning)
+	};
+
+	let refine = matches.value_of("refine")
+		.map(|count| count.parse::<usize>().unwrap());
+
+	let tabu_memory = match matches.value_of("tabu_memory").unwrap_or("genomes") {
+		"genomes" => tabu_search::Memory::Genomes,
+		"moves" => tabu_search::Memory::Moves,
+		memory => panic!("Unknown memory: {}", memory)
+	};
+
+	let tabu_tenure = matches.value_of("tabu_tenure")
+		.map_or(tabu_search::TENURE, |tenure| tenure.parse::<usize>().unwrap());
+
+	let inertia = matches.value_of("inertia")
+		.map_or(particle_swarm::INERTIA, |inertia| inertia.parse::<f32>().unwrap());
+
+	let acceleration = matches.value_of("acceleration")
+		.map_or(vec![particle_swarm::COGNITIVE, particle_swarm::SOCIAL], |acceleration| {
+			acceleration.split(',')
+				.map(|coefficient| coefficient.trim().parse::<f32>().unwrap())
+				.collect::<Vec<f32>>()
+		});
+
+	if acceleration.len() != 2 {
+		panic!("Particle swarm needs exactly two acceleration coefficients");
+	}
+
+	let neighbourhood = matches.value_of("neighbourhood")
+		.map(|radius| radius.parse::<usize>().unwrap());
+
+	let descriptors = matches.value_of("descriptors")
+		.unwrap_or("nodes,contact")
+		.split(',')
+		.map(|descriptor| match descriptor.trim() {
+			"nodes" => Descriptor::Nodes,
+			"contact" => Descriptor::GroundContact,
+			"jump" => Descriptor::JumpHeight,
+			"distance" => Descriptor::Distance,
+			_ => panic!("Unknown descriptor: {}", descriptor)
+		})
+		.collect::<Vec<Descriptor>>();
+
+	if descriptors.len() != 2 {
+		panic!("MAP-Elites needs exactly two descriptors");
+	}
+
 	println!("");
 
 	let mut sample_fitness: Vec<Vec<f32>> =
@@ -84,8 +556,19 @@ fn main () {
 	let mut sample_time: Vec<Vec<f32>> =
 		(0 .. optmethods.len()).map(|_| Vec::with_capacity(sample_size)).collect();
 
+	// Every sample is written to the same files, so they're only opened once
+	let mut pareto_file = matches.value_of("pareto").map(|path| {
+		File::create(path).expect("Could not open Pareto front file for writing")
+	});
+	let mut archive_file = matches.value_of("archive").map(|path| {
+		File::create(path).expect("Could not open archive file for writing")
+	});
+
 	for sample_index in 0 .. sample_size {
-		let population = Population::new(pop_size, &mut rng);
+		let mut population = Population::new(pop_size, &mut rng);
+		if matches.is_present("controllers") {
+			population.controller_add(&mut rng);
+		}
 
 		let mut opt: Vec<Box<OptimisationMethod>> =
 			Vec::with_capacity(optmethods.len());
@@ -93,30 +576,158 @@ fn main () {
 		for mtd in &optmethods {
 			match *mtd {
 				"GA" => opt.push(GeneticAlgorithm::new(population.clone(), false)),
-				"HC" => opt.push(HillClimbing::new(population.clone(), false)),
+				"HC" => {
+					let mut hc = HillClimbing::new(population.clone(), false, hc_strategy);
+					hc.neighbours = hc_neighbours;
+					hc.restart = hc_restart;
+					hc.adaptive = matches.is_present("hc_adaptive");
+					opt.push(hc);
+				},
 				"SA" => opt.push(SimulatedAnnealing::new(population.clone(), false)),
+				"DE" => opt.push(DifferentialEvolution::new(population.clone(), false, de_variant)),
+				"PSO" => {
+					let mut pso = ParticleSwarm::new(population.clone(), false);
+					pso.inertia = inertia;
+					pso.cognitive = acceleration[0];
+					pso.social = acceleration[1];
+					pso.neighbourhood = neighbourhood;
+					opt.push(pso);
+				},
+				"PT" => {
+					opt.push(ParallelTempering::new(
+						population.clone(),
+						false,
+						temperatures.clone()
+					));
+				},
+				"SP" => {
+					let mut sp = Speciation::new(population.clone(), false);
+					sp.threshold = compatibility;
+					opt.push(sp);
+				},
+				"IM" => {
+					let mut im = IslandModel::new(
+						population.clone(),
+						false,
+						topology,
+						islands
+					);
+					im.interval = migration;
+					opt.push(im);
+				},
+				"MA" => {
+					let mut ma = Memetic::new(population.clone(), false, learning);
+					ma.refine = refine;
+					opt.push(ma);
+				},
+				"TS" => {
+					let mut ts = TabuSearch::new(population.clone(), false, tabu_memory);
+					ts.tenure = tabu_tenure;
+					opt.push(ts);
+				},
+				"RS" => opt.push(RandomSearch::new(population.clone(), false)),
+				"CMA" => opt.push(CmaEs::new(population.clone(), false)),
+				"ES" => opt.push(EvolutionStrategies::new(population.clone(), false, es_selection)),
+				"NSGA" => opt.push(Nsga2::new(population.clone(), false, objectives.clone())),
+				"NS" => {
+					let mut ns = NoveltySearch::new(population.clone(), false);
+					ns.blend = novelty_blend;
+					opt.push(ns);
+				},
+				"ME" => opt.push(MapElites::new(
+					population.clone(),
+					false,
+					(descriptors[0], descriptors[1])
+				)),
 				_ => {}
 			}
 		}
 
+		for mtd in 0 .. opt.len() {
+			opt[mtd].simulation_set(simulation.clone());
+		}
+
 		let mut total_time = 0.0;
 		for mtd in 0 .. opt.len() {
-			for _ in 0 .. gen_count {
-				if opt[mtd].generation_single().is_err() {
-					break;
+			match budget {
+				Some(evaluations) => {
+					// Stop early if a generation simulates nothing new, as it
+					// would never use up the budget
+					while opt[mtd].get_data().cache.evaluations() < evaluations {
+						let spent = opt[mtd].get_data().cache.evaluations();
+						if opt[mtd].generation_single().is_err() ||
+						   opt[mtd].get_data().cache.evaluations() == spent {
+							break;
+						}
+					}
+				},
+				None => {
+					for _ in 0 .. gen_count {
+						if opt[mtd].generation_single().is_err() {
+							break;
+						}
+					}
 				}
 			}
 
 			let data = opt[mtd].get_data();
 			let gen_time = data.average_gen_time();
-			println!("{}   Fittest: {:4.2}   Time: {} ms",
+			println!("{}   Fittest: {:4.2}   Time: {} ms   Cache Hits: {:.1}%",
 			         data.title,
 					 data.generations_get_fittest(),
-			         gen_time
+			         gen_time,
+			         data.average_cache_hit_rate() * 100.0
 			);
+			let gen = data.generations_get_fittest_gen();
+			if let Some(stats) = data.creature_get_fittest(gen).fitness_stats {
+				println!("    Min: {:4.2}   Variance: {:4.2}",
+				         stats.min,
+				         stats.variance
+				);
+			}
+			let (nodes, muscles) = data.generations[data.gen].complexity_average();
+			println!("    Avg. Nodes: {:.2}   Avg. Muscles: {:.2}", nodes, muscles);
+			if let Some(groups) = data.groups.last() {
+				for (idx, stats) in groups.iter().enumerate() {
+					println!("    Group {}: Size: {}   Fittest: {:4.2}   Average: {:4.2}",
+					         idx, stats.size, stats.fittest, stats.average
+					);
+				}
+			}
+			if budget.is_some() {
+				println!("    Generations: {}   Evaluations: {}", data.gen, data.cache.evaluations());
+			}
+			if let (Some(first), Some(last)) =
+				(opt[mtd].step_size(0), opt[mtd].step_size(data.gen)) {
+				println!("    Step Size: {:.4} -> {:.4}", first, last);
+			}
+			if let Some(size) = opt[mtd].tabu_size(data.gen) {
+				let hits = (0 .. data.gen + 1)
+					.filter_map(|gen| opt[mtd].aspiration_hits(gen))
+					.fold(0, |sum, hits| sum + hits);
+				println!("    Tabu Size: {:.2}   Aspiration Hits: {}", size, hits);
+			}
+			if let Some(rates) = opt[mtd].swap_rates() {
+				let rates = rates.iter()
+					.map(|rate| format!("{:.1}%", rate * 100.0))
+					.collect::<Vec<String>>();
+				println!("    Swap Rates: {}", rates.join(", "));
+			}
 			sample_fitness[mtd].push(data.generations_get_fittest());
 			sample_time[mtd].push(gen_time);
 			total_time += gen_time;
+
+			if let Some(strength) = robustness {
+				robustness_test(data, strength);
+			}
+
+			if let Some(ref mut buffer) = pareto_file {
+				pareto_export(&*opt[mtd], buffer, sample_index);
+			}
+
+			if let Some(ref mut buffer) = archive_file {
+				archive_export(&*opt[mtd], buffer, sample_index);
+			}
 		}
 		println!(
 			"\nEst. Time Remaining: {:.2} secs\n", ((total_time as usize * gen_count) * (sample_size - (sample_index + 1))) as f64 / 1000.0
@@ -143,3 +754,203 @@ fn main () {
 		);
 	}
 }
+
+/// Writes the Pareto front of every generation in a sample to a CSV file, one
+/// creature per line, and shows the final generation's front
+fn pareto_export(method: &OptimisationMethod, buffer: &mut File, sample: usize) {
+	let data = method.get_data();
+	let objectives = method.objectives();
+	let titles = objectives.iter()
+		.map(|objective| objective.title())
+		.collect::<Vec<&str>>()
+		.join(",");
+
+	let front_last = match method.pareto_front(data.gen) {
+		Some(front) => front,
+		None => return
+	};
+
+	if sample == 0 {
+		write!(buffer, "Sample,Generation,{}\n", titles)
+			.expect("Could not write data");
+	}
+
+	for gen in 0 .. data.gen + 1 {
+		for point in method.pareto_front(gen).unwrap_or(&Vec::new()) {
+			let values = point.iter()
+				.map(|value| value.to_string())
+				.collect::<Vec<String>>()
+				.join(",");
+			write!(buffer, "{},{},{}\n", sample, gen, values)
+				.expect("Could not write data");
+		}
+	}
+
+	println!("    Pareto front ({} creatures): {}", front_last.len(), titles);
+	for point in front_last {
+		let values = point.iter()
+			.map(|value| format!("{:8.2}", value))
+			.collect::<Vec<String>>()
+			.join("  ");
+		println!("      {}", values);
+	}
+}
+
+/// Writes every occupied cell of a sample's MAP-Elites archive to a CSV file,
+/// with the range of descriptor values each cell covers, and shows how full
+/// it is
+fn archive_export(method: &OptimisationMethod, buffer: &mut File, sample: usize) {
+	let archive = match method.archive() {
+		Some(archive) => archive,
+		None => return
+	};
+	let (first, second) = archive.descriptors;
+
+	if sample == 0 {
+		write!(
+			buffer, "Sample,Column,Row,{0} From,{0} To,{1} From,{1} To,Fitness\n",
+			first.title(), second.title()
+		).expect("Could not write data");
+	}
+
+	for cell in archive.elites() {
+		let (x, y) = archive.position(cell);
+		let first_range = first.bin_range(x);
+		let second_range = second.bin_range(y);
+
+		write!(
+			buffer, "{},{},{},{},{},{},{},{}\n",
+			sample, x, y,
+			first_range.start, first_range.end,
+			second_range.start, second_range.end,
+			archive.cells[cell].as_ref().unwrap().fitness
+		).expect("Could not write data");
+	}
+
+	println!("    Archive: {} of {} cells filled ({} x {})",
+	         archive.elites().len(),
+	         archive.cells.len(),
+	         first.title(),
+	         second.title()
+	);
+}
+
+/// Re-simulates the fittest creature from the latest generation in a steady
+/// headwind and in gusts, to see how well its gait copes with the weather
+fn robustness_test(data: &OpMethodData, strength: f32) {
+	let gen = data.generations.len() - 1;
+	let fittest = data.creature_get_fittest(gen);
+
+	let settings = vec![
+		("Headwind", Simulation {
+			forces: Simulation::headwind(strength).forces,
+			.. data.simulation.clone()
+		}),
+		("Gusts", Simulation {
+			forces: vec![
+				ForceField::Gust { x: -strength, y: 0.0, period: 120 }
+			],
+			.. data.simulation.clone()
+		})
+	];
+
+	for &(title, ref simulation) in &settings {
+		let mut creature = fittest.clone();
+		physics::full_simulation_creature(&mut creature, simulation);
+		println!("    {}: {:4.2}", title, creature.fitness);
+	}
+}
+
+/// Simulates the same population with and without ending simulations early,
+/// and compares how long each took and how much the fitness values differ
+fn benchmark_early_exit(pop_size: usize, rng: &mut rand::ThreadRng) {
+	let population = Population::new(pop_size, rng);
+	let settings = vec![
+		("Full", Simulation::default()),
+		("Early Exit", Simulation::early_exit())
+	];
+
+	let mut results: Vec<(f32, u64, Population)> = Vec::with_capacity(2);
+
+	for &(title, ref simulation) in &settings {
+		let mut pop = population.clone();
+		let mut frames: u64 = 0;
+
+		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+		for creature in &mut pop.creatures {
+			frames += physics::full_simulation_creature(creature, simulation)
+				as u64;
+		}
+		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		println!(
+			"{}:\n    Time:\t\t{:.2} ms\n    Frames:\t\t{}\n    Average Fitness:\t{}",
+			title, time_end - time_start, frames, pop.fitness_average()
+		);
+		results.push((time_end - time_start, frames, pop));
+	}
+
+	// Creatures are simulated in the same order, so compare them one by one
+	let mut difference = 0.0;
+	for idx in 0 .. pop_size {
+		difference += (results[0].2.creatures[idx].fitness -
+		               results[1].2.creatures[idx].fitness).abs();
+	}
+
+	println!(
+		"\nSpeed-up: {:.2}x\nFrames skipped: {:.1}%\nAverage fitness difference: {}",
+		results[0].0 / results[1].0,
+		100.0 - (results[1].1 as f64 * 100.0 / results[0].1 as f64),
+		difference / pop_size as f32
+	);
+}
+
+/// Simulates the same population one creature at a time, as a single batch,
+/// and in batches spread across every thread, comparing how many creatures
+/// each one simulates per second and how much their fitness values differ
+fn benchmark_batch(pop_size: usize, rng: &mut rand::ThreadRng) {
+	let population = Population::new(pop_size, rng);
+	let simulation = Simulation::default();
+
+	let mut single = population.clone();
+	let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+	physics::full_simulation_population(&mut single, &simulation);
+	let time_single = time::precise_time_ns() as f32 / 1_000_000.0 - time_start;
+
+	let mut batched = population.clone();
+	let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+	batch::simulate_creatures(&mut batched.creatures, &simulation);
+	let time_batched = time::precise_time_ns() as f32 / 1_000_000.0 - time_start;
+
+	let mut parallel = population.clone();
+	let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+	batch::simulate_population(&mut parallel, &simulation);
+	let time_parallel = time::precise_time_ns() as f32 / 1_000_000.0 - time_start;
+
+	let results = vec![
+		("One at a time", time_single, &single),
+		("Single batch", time_batched, &batched),
+		("Parallel batches", time_parallel, &parallel)
+	];
+
+	for &(title, time, pop) in &results {
+		let mut difference: f32 = 0.0;
+		let mut difference_max: f32 = 0.0;
+		for idx in 0 .. pop_size {
+			let diff = (single.creatures[idx].fitness -
+			            pop.creatures[idx].fitness).abs();
+			difference += diff;
+			difference_max = difference_max.max(diff);
+		}
+
+		println!(
+			"{}:\n    Time:\t\t{:.2} ms\n    Creatures/sec:\t{:.0}\n    Speed-up:\t\t{:.2}x\n    Fitness difference:\t{} avg, {} max",
+			title,
+			time,
+			pop_size as f32 * 1000.0 / time,
+			time_single / time,
+			difference / pop_size as f32,
+			difference_max
+		);
+	}
+}
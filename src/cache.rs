@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use creature::Creature;
+use physics::{self, Simulation, Behaviour};
+
+/// Most fitness values kept at once, after which the cache is emptied so it
+/// can't use up too much memory over a long run
+pub const CACHE_CAPACITY: usize = 1 << 20;
+
+/// Results of a creature's simulation, which can be given to an identical
+/// creature instead of simulating it again
+#[derive(Clone)]
+struct Evaluation {
+	fitness: f32,
+	energy: f32,
+	behaviour: Behaviour
+}
+
+/// Results of creatures that have already been simulated, keyed by
+/// their genome hash, so identical creatures are only ever simulated once.
+/// It can be shared between threads while a generation is simulated.
+pub struct FitnessCache {
+	fitness: Mutex<HashMap<u64, Evaluation>>,
+	hits: AtomicUsize,
+	misses: AtomicUsize,
+
+	// Number of creatures actually simulated, which is never reset
+	evaluations: AtomicUsize
+}
+
+impl FitnessCache {
+	pub fn new() -> FitnessCache {
+		FitnessCache {
+			fitness: Mutex::new(HashMap::new()),
+			hits: AtomicUsize::new(0),
+			misses: AtomicUsize::new(0),
+			evaluations: AtomicUsize::new(0)
+		}
+	}
+
+	/// Gives a creature its fitness from the cache if an identical creature
+	/// has been simulated before, otherwise simulates it and adds its fitness
+	/// to the cache. Perturbed simulations give a different fitness each
+	/// time, so they are never cached.
+	pub fn simulate(&self, creature: &mut Creature, simulation: &Simulation) {
+		if simulation.perturbation.is_some() {
+			self.evaluations.fetch_add(1, Ordering::Relaxed);
+			physics::full_simulation_creature(creature, simulation);
+			return;
+		}
+
+		let hash = creature.genome_hash();
+		let cached = self.fitness.lock().unwrap().get(&hash).cloned();
+
+		match cached {
+			Some(evaluation) => {
+				self.hits.fetch_add(1, Ordering::Relaxed);
+				creature.fitness = evaluation.fitness;
+				creature.energy = evaluation.energy;
+				creature.behaviour = evaluation.behaviour;
+				creature.fitness_stats = None;
+			},
+			None => {
+				self.misses.fetch_add(1, Ordering::Relaxed);
+				self.evaluations.fetch_add(1, Ordering::Relaxed);
+				physics::full_simulation_creature(creature, simulation);
+
+				let mut cache = self.fitness.lock().unwrap();
+				if cache.len() >= CACHE_CAPACITY {
+					cache.clear();
+				}
+				cache.insert(hash, Evaluation {
+					fitness: creature.fitness,
+					energy: creature.energy,
+					behaviour: creature.behaviour.clone()
+				});
+			}
+		}
+	}
+
+	/// Returns the fraction of creatures found in the cache since the hit
+	/// rate was last reset, then resets it
+	pub fn hit_rate_take(&self) -> f32 {
+		let hits = self.hits.swap(0, Ordering::Relaxed);
+		let misses = self.misses.swap(0, Ordering::Relaxed);
+
+		if hits + misses == 0 { return 0.0 }
+		hits as f32 / (hits + misses) as f32
+	}
+
+	/// Returns the number of creatures simulated rather than found in the
+	/// cache, so methods can be compared on the same budget
+	pub fn evaluations(&self) -> usize {
+		self.evaluations.load(Ordering::Relaxed)
+	}
+
+	/// Returns the number of fitness values in the cache
+	pub fn len(&self) -> usize {
+		self.fitness.lock().unwrap().len()
+	}
+
+	/// Removes every fitness value, for when the simulation settings change
+	pub fn clear(&self) {
+		self.fitness.lock().unwrap().clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use cache::FitnessCache;
+	use creature::Creature;
+	use physics::Simulation;
+
+	/// Simulate the same creature twice, making sure the second time comes
+	/// from the cache with the same fitness
+	#[test]
+	fn simulate_cached() {
+		let mut rng = rand::thread_rng();
+		let cache = FitnessCache::new();
+		let simulation = Simulation::default();
+
+		let mut creature = Creature::new(&mut rng);
+		let mut duplicate = creature.clone();
+
+		cache.simulate(&mut creature, &simulation);
+		duplicate.fitness = 0.0;
+		cache.simulate(&mut duplicate, &simulation);
+
+		assert_eq!(cache.len(), 1);
+		assert_eq!(cache.evaluations(), 1);
+		assert_approx_eq!(creature.fitness, duplicate.fitness);
+		assert_approx_eq!(cache.hit_rate_take(), 0.5);
+		assert_approx_eq!(cache.hit_rate_take(), 0.0);
+	}
+
+	/// Make sure perturbed simulations are never cached
+	#[test]
+	fn simulate_perturbed() {
+		let mut rng = rand::thread_rng();
+		let cache = FitnessCache::new();
+		let simulation = Simulation::robust(2);
+
+		let mut creature = Creature::new(&mut rng);
+		cache.simulate(&mut creature, &simulation);
+		cache.simulate(&mut creature, &simulation);
+
+		assert_eq!(cache.len(), 0);
+		assert_eq!(cache.evaluations(), 2);
+		assert!(creature.fitness_stats.is_some());
+	}
+}
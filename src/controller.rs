@@ -0,0 +1,198 @@
+use std::ops::Range;
+use rand::ThreadRng;
+use creature::{Creature, NodePair, RangeBounds};
+use physics::{Simulation, Environment};
+
+/// Number of values each neuron receives every frame:
+///  0-1: Whether each of the muscle's two nodes are touching the ground
+///  2-3: The body's angle, as its sine and cosine
+///  4-5: The body's average X and Y velocity
+///  6:   The muscle's own timer, as a value between -1 and 1
+///  7:   The neuron's output from the previous frame
+pub const NEURON_INPUTS: usize = 8;
+pub const BOUNDS_NEURON_WEIGHT: Range<f32> = -2.0 .. 2.0;
+
+/// Velocities are divided by this before being given to a neuron, so that
+/// they are roughly in the same range as the other inputs
+const VELOCITY_SCALE: f32 = 10.0;
+
+/// A single recurrent neuron which decides how contracted its muscle should
+/// be, based on what the creature can currently sense
+#[derive(Clone)]
+pub struct Neuron {
+	pub weights: [f32; NEURON_INPUTS], // Evolution property
+	pub bias: f32,                     // Evolution property
+	pub output: f32                    // Physics property
+}
+
+/// Everything a creature can sense about itself during a single frame
+pub struct Sensors {
+	pub contact: Vec<bool>,
+	pub angle: f32,
+	pub vx: f32,
+	pub vy: f32
+}
+
+impl Neuron {
+	/// Generates a neuron with random weights within their bounds
+	pub fn random(rng: &mut ThreadRng) -> Neuron {
+		let mut weights = [0.0; NEURON_INPUTS];
+		for weight in weights.iter_mut() {
+			*weight = BOUNDS_NEURON_WEIGHT.gen(rng);
+		}
+
+		Neuron {
+			weights: weights,
+			bias: BOUNDS_NEURON_WEIGHT.gen(rng),
+			output: 0.0
+		}
+	}
+
+	/// Feeds the inputs through the neuron, returning how contracted the
+	/// muscle should be, from 0.0 (fully extended) to 1.0 (fully contracted)
+	pub fn activate(&mut self, inputs: &[f32; NEURON_INPUTS]) -> f32 {
+		let mut sum = self.bias;
+		for idx in 0 .. NEURON_INPUTS {
+			sum += self.weights[idx] * inputs[idx];
+		}
+
+		self.output = sum.tanh();
+		(self.output + 1.0) * 0.5
+	}
+}
+
+impl Sensors {
+	/// Reads the current state of a creature's body. Nodes only touch the
+	/// ground on land, where the floor's height depends on the slope.
+	pub fn read(creature: &Creature, simulation: &Simulation) -> Sensors {
+		let node_len = creature.nodes.len() as f32;
+		let (mut cx, mut cy, mut vx, mut vy) = (0.0, 0.0, 0.0, 0.0);
+
+		for node in &creature.nodes {
+			cx += node.x;
+			cy += node.y;
+			vx += node.vx;
+			vy += node.vy;
+		}
+
+		cx /= node_len;
+		cy /= node_len;
+
+		// The body's angle is taken from its centre to its first node
+		let angle = match creature.nodes.first() {
+			Some(node) => (node.y - cy).atan2(node.x - cx),
+			None => 0.0
+		};
+
+		Sensors {
+			contact: creature.nodes.iter().map(|node| {
+				simulation.environment == Environment::Land &&
+				node.y >= simulation.floor(node.x)
+			}).collect(),
+			angle: angle,
+			vx: vx / node_len,
+			vy: vy / node_len
+		}
+	}
+
+	/// Returns the inputs for a neuron attached to a muscle between the
+	/// given nodes, given the value of the muscle's own timer
+	pub fn inputs(&self, nodes: &NodePair, neuron: &Neuron, wave: f32)
+	    -> [f32; NEURON_INPUTS] {
+		let contact = |idx: usize| if self.contact[idx] { 1.0 } else { 0.0 };
+
+		[
+			contact(nodes.0),
+			contact(nodes.1),
+			self.angle.sin(),
+			self.angle.cos(),
+			self.vx / VELOCITY_SCALE,
+			self.vy / VELOCITY_SCALE,
+			(wave * 2.0) - 1.0,
+			neuron.output
+		]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use controller::{self, Neuron, Sensors};
+	use creature::{Creature, NodePair, BOUNDS_NODE_X, BOUNDS_NODE_Y};
+	use physics::Simulation;
+
+	/// Make sure a randomly generated neuron's weights are within bounds
+	#[test]
+	fn neuron_random() {
+		let mut rng = rand::thread_rng();
+		let neuron = Neuron::random(&mut rng);
+
+		for weight in neuron.weights.iter() {
+			assert!(
+				*weight >= controller::BOUNDS_NEURON_WEIGHT.start &&
+				*weight < controller::BOUNDS_NEURON_WEIGHT.end
+			);
+		}
+		assert_eq!(neuron.output, 0.0);
+	}
+
+	/// Make sure the neuron always returns a valid contraction, and remembers
+	/// its output for the next frame
+	#[test]
+	fn neuron_activate() {
+		let mut rng = rand::thread_rng();
+		let mut neuron = Neuron::random(&mut rng);
+
+		for _ in 0 .. 10 {
+			let contraction = neuron.activate(&[1.0; controller::NEURON_INPUTS]);
+			assert!(contraction >= 0.0 && contraction <= 1.0);
+			assert_approx_eq!(contraction, (neuron.output + 1.0) * 0.5);
+		}
+	}
+
+	/// Make sure the sensors correctly detect which nodes touch the ground
+	#[test]
+	fn sensors_contact() {
+		let mut rng = rand::thread_rng();
+		let mut creature = Creature::empty();
+		let _ = creature.add_node(Creature::add_node_random(&mut rng));
+		let _ = creature.add_node(Creature::add_node_random(&mut rng));
+
+		creature.nodes[0].y = BOUNDS_NODE_Y.end;
+		creature.nodes[1].y = BOUNDS_NODE_Y.start;
+
+		let sensors = Sensors::read(&creature, &Simulation::default());
+		assert!(sensors.contact[0]);
+		assert!(!sensors.contact[1]);
+
+		let neuron = Neuron::random(&mut rng);
+		let inputs = sensors.inputs(&NodePair(0, 1), &neuron, 1.0);
+		assert_eq!(inputs[0], 1.0);
+		assert_eq!(inputs[1], 0.0);
+		assert_eq!(inputs[6], 1.0);
+
+		let water = Simulation::water();
+		assert!(!Sensors::read(&creature, &water).contact[0]);
+	}
+
+	/// Make sure a node resting on a slope senses the ground beneath it,
+	/// even though it's above the flat floor
+	#[test]
+	fn sensors_contact_slope() {
+		let mut rng = rand::thread_rng();
+		let mut creature = Creature::empty();
+		let _ = creature.add_node(Creature::add_node_random(&mut rng));
+		let _ = creature.add_node(Creature::add_node_random(&mut rng));
+
+		let simulation = Simulation { slope: 0.5, .. Simulation::default() };
+		creature.nodes[0].x = BOUNDS_NODE_X.end;
+		creature.nodes[0].y = simulation.floor(BOUNDS_NODE_X.end);
+		creature.nodes[1].x = 0.0;
+		creature.nodes[1].y = BOUNDS_NODE_Y.end;
+
+		let sensors = Sensors::read(&creature, &simulation);
+		assert!(creature.nodes[0].y < BOUNDS_NODE_Y.end);
+		assert!(sensors.contact[0]);
+		assert!(!sensors.contact[1]);
+	}
+}
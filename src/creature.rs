@@ -1,7 +1,12 @@
-use std::cmp::{PartialOrd, Ordering};
+use std::cmp::{self, PartialOrd, Ordering};
+use std::collections::hash_map::DefaultHasher;
+use std::f32;
+use std::hash::{Hash, Hasher};
 use std::ops::Range;
 use rand::{Rng, ThreadRng};
 use rand::distributions::range::SampleRange;
+use controller::Neuron;
+use physics::{FitnessStats, Behaviour, Simulation};
 
 /// Constants to define a creatures lower and upper exclusive bounds.
 /// eg. a creature can have only 3 to 6 nodes. Any less and its useless,
@@ -13,10 +18,25 @@ pub const BOUNDS_NODE_FRICTION: Range<f32> = 0.1 .. 0.92;
 pub const BOUNDS_MUSCLE_STRENGTH: Range<f32> = 1.0 .. 10.0;
 pub const BOUNDS_MUSCLE_TIME_EXTENDED: Range<u32> = 30 .. 100;
 pub const BOUNDS_MUSCLE_TIME_CONTRACTED: Range<u32> = 30 .. 100;
+pub const BOUNDS_MUSCLE_PHASE: Range<u32> = 0 .. 200;
 
-pub const BOUNDS_MUSCLE_LENGTH: Range<f32> = 0.75 .. 1.2;
+pub const BOUNDS_MUSCLE_CONTRACTION: Range<f32> = 0.5 .. 0.95;
+pub const BOUNDS_MUSCLE_EXTENSION: Range<f32> = 1.05 .. 1.5;
 pub const NODE_RADIUS: f32 = 16.0;
 
+/// The contraction and extension ratios given to new muscles
+pub const DEFAULT_MUSCLE_CONTRACTION: f32 = 0.75;
+pub const DEFAULT_MUSCLE_EXTENSION: f32 = 1.2;
+
+/// Number of continuous genes each node and muscle adds to a creature's genes
+pub const GENES_NODE: usize = 3;
+pub const GENES_MUSCLE: usize = 6;
+
+/// How much the average difference between two creatures' shared genes
+/// counts towards their compatibility distance, compared to a single node or
+/// muscle only one of them has
+pub const COMPATIBILITY_GENES: f32 = 2.0;
+
 /// Add "gen" function to range, which will return a random
 /// value between its lower and upper bounds
 pub trait RangeBounds<T> {
@@ -33,7 +53,17 @@ impl<T: Copy> RangeBounds<T> for Range<T> where T: PartialOrd + SampleRange {
 pub struct Creature {
 	pub nodes: Vec<Node>,
 	pub muscles: Vec<Muscle>,
-	pub fitness: f32
+	pub fitness: f32,
+
+	// Spread of fitness values when the creature was last simulated several
+	// times with small changes, or None if it was only simulated once
+	pub fitness_stats: Option<FitnessStats>,
+
+	// Total force used by every muscle during the creature's last simulation
+	pub energy: f32,
+
+	// How the creature moved during its last simulation
+	pub behaviour: Behaviour
 }
 
 /// These traits are implemented so that we can return the creature with
@@ -91,11 +121,39 @@ pub struct Muscle {
 	pub len: f32,             // Evolution property
 	pub len_max: f32,         // Evolution property
 	pub len_min: f32,         // Evolution property
+	pub contraction: f32,     // Evolution property
+	pub extension: f32,       // Evolution property
 	pub time_extended: u32,   // Evolution property
 	pub time_contracted: u32, // Evolution property
+	pub phase: u32,           // Evolution property
+	pub waveform: Waveform,   // Evolution property
+	pub neuron: Option<Neuron>, // Evolution property
 	pub contracted: bool
 }
 
+/// Measurements of a creature which can be optimised at the same time. Each
+/// one's value is larger for a better creature.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Objective {
+	/// How far the creature travels, the same as its fitness
+	Distance,
+
+	/// How little force its muscles use to get there
+	Energy,
+
+	/// How few nodes and muscles it is made up of
+	Simplicity
+}
+
+/// The shape of the wave a muscle follows when moving between its extended
+/// and contracted lengths
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Waveform {
+	Square,
+	Sine,
+	Triangle
+}
+
 impl Creature {
 	/// Generates a new creature with random property values within
 	/// their bounds
@@ -121,7 +179,10 @@ impl Creature {
 		Creature {
 			nodes: nodes,
 			muscles: muscles,
-			fitness: 0.0
+			fitness: 0.0,
+			fitness_stats: None,
+			energy: 0.0,
+			behaviour: Behaviour::default()
 		}
 	}
 
@@ -130,7 +191,10 @@ impl Creature {
 		Creature {
 			nodes: Vec::new(),
 			muscles: Vec::new(),
-			fitness: 0.0
+			fitness: 0.0,
+			fitness_stats: None,
+			energy: 0.0,
+			behaviour: Behaviour::default()
 		}
 	}
 
@@ -201,10 +265,15 @@ impl Creature {
 			nodes: nodepair,
 			strength: BOUNDS_MUSCLE_STRENGTH.gen(rng),
 			len: len,
-			len_min: len * BOUNDS_MUSCLE_LENGTH.start,
-			len_max: len * BOUNDS_MUSCLE_LENGTH.end,
+			len_min: len * DEFAULT_MUSCLE_CONTRACTION,
+			len_max: len * DEFAULT_MUSCLE_EXTENSION,
+			contraction: DEFAULT_MUSCLE_CONTRACTION,
+			extension: DEFAULT_MUSCLE_EXTENSION,
 			time_extended: BOUNDS_MUSCLE_TIME_EXTENDED.gen(rng),
 			time_contracted: BOUNDS_MUSCLE_TIME_CONTRACTED.gen(rng),
+			phase: BOUNDS_MUSCLE_PHASE.gen(rng),
+			waveform: Waveform::random(rng),
+			neuron: None,
 			contracted: false
 		}
 	}
@@ -264,12 +333,248 @@ impl Creature {
 			node.vx = 0.0;
 			node.vy = 0.0;
 		}
+		for muscle in &mut self.muscles {
+			if let Some(ref mut neuron) = muscle.neuron {
+				neuron.output = 0.0;
+			}
+		}
+	}
+
+	/// Moves the creature vertically so that the node nearest the floor rests
+	/// on it, leaving its starting position alone
+	pub fn ground_start(&mut self, simulation: &Simulation) {
+		if self.nodes.is_empty() { return }
+
+		let gap = self.clearance(simulation);
+		for node in &mut self.nodes {
+			node.y += gap;
+		}
+	}
+
+	/// Returns how far above the floor the creature's lowest node is, which
+	/// is zero or less while it's touching the ground
+	pub fn clearance(&self, simulation: &Simulation) -> f32 {
+		self.nodes.iter().fold(f32::MAX, |gap, node| {
+			gap.min(simulation.floor(node.x) - node.y)
+		})
+	}
+
+	/// Gives every muscle without one a neural controller, so the creature
+	/// reacts to what it senses rather than only following its timers
+	pub fn controller_add(&mut self, rng: &mut ThreadRng) {
+		for muscle in &mut self.muscles {
+			if muscle.neuron.is_none() {
+				muscle.neuron = Some(Neuron::random(rng));
+			}
+		}
+	}
+
+	/// Returns whether any of the creature's muscles are driven by a neural
+	/// controller
+	pub fn has_controller(&self) -> bool {
+		self.muscles.iter().any(|muscle| muscle.neuron.is_some())
+	}
+
+	/// Returns a hash of every evolution property, so two creatures with the
+	/// same genome are given the same hash no matter where they currently are
+	/// in a simulation or what their fitness is
+	pub fn genome_hash(&self) -> u64 {
+		let mut hasher = DefaultHasher::new();
+
+		for node in &self.nodes {
+			hash_f32(node.start_x, &mut hasher);
+			hash_f32(node.start_y, &mut hasher);
+			hash_f32(node.friction, &mut hasher);
+		}
+
+		for muscle in &self.muscles {
+			muscle.nodes.0.hash(&mut hasher);
+			muscle.nodes.1.hash(&mut hasher);
+			hash_f32(muscle.strength, &mut hasher);
+			hash_f32(muscle.len, &mut hasher);
+			hash_f32(muscle.contraction, &mut hasher);
+			hash_f32(muscle.extension, &mut hasher);
+			muscle.time_extended.hash(&mut hasher);
+			muscle.time_contracted.hash(&mut hasher);
+			muscle.phase.hash(&mut hasher);
+			(muscle.waveform as u8).hash(&mut hasher);
+
+			match muscle.neuron {
+				Some(ref neuron) => {
+					for weight in neuron.weights.iter() {
+						hash_f32(*weight, &mut hasher);
+					}
+					hash_f32(neuron.bias, &mut hasher);
+				},
+				None => 0u8.hash(&mut hasher)
+			}
+		}
+
+		hasher.finish()
+	}
+
+	/// Returns every continuous evolution property as a list of genes, each
+	/// scaled between 0 and 1 by its bounds, so methods which optimise a list
+	/// of numbers can work on creatures directly. The node start positions
+	/// and friction come first, followed by each muscle's strength, lengths
+	/// and timings. Waveforms and neural controllers are left out.
+	pub fn genes(&self) -> Vec<f32> {
+		let mut genes = Vec::with_capacity(
+			self.nodes.len() * GENES_NODE + self.muscles.len() * GENES_MUSCLE
+		);
+
+		for node in &self.nodes {
+			genes.push(gene_normalise(node.start_x, BOUNDS_NODE_X));
+			genes.push(gene_normalise(node.start_y, BOUNDS_NODE_Y));
+			genes.push(gene_normalise(node.friction, BOUNDS_NODE_FRICTION));
+		}
+
+		for muscle in &self.muscles {
+			genes.push(
+				gene_normalise(muscle.strength, BOUNDS_MUSCLE_STRENGTH)
+			);
+			genes.push(
+				gene_normalise(muscle.contraction, BOUNDS_MUSCLE_CONTRACTION)
+			);
+			genes.push(
+				gene_normalise(muscle.extension, BOUNDS_MUSCLE_EXTENSION)
+			);
+			genes.push(gene_normalise(
+				muscle.time_extended as f32,
+				range_f32(BOUNDS_MUSCLE_TIME_EXTENDED)
+			));
+			genes.push(gene_normalise(
+				muscle.time_contracted as f32,
+				range_f32(BOUNDS_MUSCLE_TIME_CONTRACTED)
+			));
+			genes.push(gene_normalise(
+				muscle.phase as f32,
+				range_f32(BOUNDS_MUSCLE_PHASE)
+			));
+		}
+
+		genes
+	}
+
+	/// Sets every continuous evolution property from a list of genes in the
+	/// same order as `genes` returns them, keeping each within its bounds.
+	/// The genes must come from a creature with the same topology.
+	pub fn genes_set(&mut self, genes: &[f32]) {
+		let (node_genes, muscle_genes) =
+			genes.split_at(self.nodes.len() * GENES_NODE);
+
+		for (node, gene) in self.nodes.iter_mut()
+			.zip(node_genes.chunks(GENES_NODE)) {
+			node.start_x = gene_value(gene[0], BOUNDS_NODE_X);
+			node.start_y = gene_value(gene[1], BOUNDS_NODE_Y);
+			node.friction = gene_value(gene[2], BOUNDS_NODE_FRICTION);
+		}
+
+		// Move the nodes to their new starting positions before the muscle
+		// lengths are recalculated from them
+		self.reset_position();
+
+		for (muscle, gene) in self.muscles.iter_mut()
+			.zip(muscle_genes.chunks(GENES_MUSCLE)) {
+			muscle.strength = gene_value(gene[0], BOUNDS_MUSCLE_STRENGTH);
+			muscle.contraction =
+				gene_value(gene[1], BOUNDS_MUSCLE_CONTRACTION);
+			muscle.extension = gene_value(gene[2], BOUNDS_MUSCLE_EXTENSION);
+			muscle.time_extended = gene_value(
+				gene[3], range_f32(BOUNDS_MUSCLE_TIME_EXTENDED)
+			).round() as u32;
+			muscle.time_contracted = gene_value(
+				gene[4], range_f32(BOUNDS_MUSCLE_TIME_CONTRACTED)
+			).round() as u32;
+			muscle.phase = gene_value(
+				gene[5], range_f32(BOUNDS_MUSCLE_PHASE)
+			).round() as u32;
+			muscle.update_length(&self.nodes);
+		}
+	}
+
+	/// Returns whether two creatures have the same number of nodes connected
+	/// by the same muscles, so each of their genes mean the same thing
+	pub fn same_topology(&self, other: &Creature) -> bool {
+		self.nodes.len() == other.nodes.len() &&
+		self.muscles.len() == other.muscles.len() &&
+		self.muscles.iter().zip(other.muscles.iter()).all(|(a, b)| {
+			a.nodes.0 == b.nodes.0 && a.nodes.1 == b.nodes.1
+		})
+	}
+
+	/// Returns how different two creatures' body plans and genes are, with a
+	/// distance of 1 for every node or muscle only one of them has, plus the
+	/// average difference between the genes of the nodes and muscles they
+	/// share
+	pub fn compatibility(&self, other: &Creature) -> f32 {
+		let nodes = (self.nodes.len() as i32 - other.nodes.len() as i32).abs();
+
+		// Muscles are matched by the nodes they join, whichever way round
+		let joins = |muscle: &Muscle| (
+			cmp::min(muscle.nodes.0, muscle.nodes.1),
+			cmp::max(muscle.nodes.0, muscle.nodes.1)
+		);
+
+		let mut unmatched = other.muscles.iter()
+			.map(&joins)
+			.collect::<Vec<(usize, usize)>>();
+		let mut muscles = 0;
+		for muscle in &self.muscles {
+			let join = joins(muscle);
+			match unmatched.iter().position(|&other| other == join) {
+				Some(idx) => { unmatched.swap_remove(idx); },
+				None => muscles += 1
+			}
+		}
+		muscles += unmatched.len() as i32;
+
+		// Nodes and muscles are compared by their position in the creature,
+		// so only as many as the smaller creature has are shared
+		let genes_self = self.genes();
+		let genes_other = other.genes();
+		let (nodes_self, muscles_self) =
+			genes_self.split_at(self.nodes.len() * GENES_NODE);
+		let (nodes_other, muscles_other) =
+			genes_other.split_at(other.nodes.len() * GENES_NODE);
+
+		let differences = nodes_self.iter().zip(nodes_other.iter())
+			.chain(muscles_self.iter().zip(muscles_other.iter()))
+			.map(|(a, b)| (a - b).abs())
+			.collect::<Vec<f32>>();
+
+		let genes = if differences.is_empty() {
+			0.0
+		} else {
+			differences.iter().fold(0.0, |sum, difference| sum + difference) /
+				differences.len() as f32
+		};
+
+		(nodes + muscles) as f32 + COMPATIBILITY_GENES * genes
+	}
+
+	/// Returns the creature's value for each of the given objectives
+	pub fn objectives(&self, objectives: &[Objective]) -> Vec<f32> {
+		objectives.iter().map(|objective| objective.value(self)).collect()
 	}
 
 	pub fn calculate_fitness(&mut self) {
 		self.fitness = self.fitness();
 	}
 
+	/// Returns the creature's centre, the average position of its nodes
+	pub fn centre(&self) -> (f32, f32) {
+		let node_len = self.nodes.len() as f32;
+		let (mut x, mut y) = (0.0, 0.0);
+
+		for node in &self.nodes {
+			x += node.x;
+			y += node.y;
+		}
+
+		(x / node_len, y / node_len)
+	}
+
 	/// Calculate the creature's fitness by averaging each node's X position
 	pub fn fitness(&self) -> f32 {
 		let mut fitness = 0.0;
@@ -283,6 +588,29 @@ impl Creature {
 	}
 }
 
+/// Scales a value between 0 and 1 by where it lies within its bounds
+fn gene_normalise(value: f32, range: Range<f32>) -> f32 {
+	(value - range.start) / (range.end - range.start)
+}
+
+/// Scales a gene between 0 and 1 back up to a value within its bounds
+fn gene_value(gene: f32, range: Range<f32>) -> f32 {
+	let value = range.start + gene * (range.end - range.start);
+	value.max(range.start).min(range.end)
+}
+
+/// Converts the bounds of a whole number property so its genes can be scaled
+/// the same way as every other property
+fn range_f32(range: Range<u32>) -> Range<f32> {
+	range.start as f32 .. range.end as f32
+}
+
+/// Adds a float's exact bit pattern to a hash, as floats can't be hashed
+/// directly
+fn hash_f32(value: f32, hasher: &mut DefaultHasher) {
+	value.to_bits().hash(hasher);
+}
+
 impl Node {
 	/// Returns the distance between one node and another using pythagoras
 	pub fn distance(&self, to: &Node) -> f32 {
@@ -298,15 +626,25 @@ impl Muscle {
 	/// are actually in range
 	pub fn range(&self, max: usize, rng: &mut ThreadRng) -> Muscle {
 		let mut new_muscle = self.clone();
-		if new_muscle.nodes.0 >= max {
-			new_muscle.nodes.0 = rng.gen_range(0, max);
-		}
-		if new_muscle.nodes.1 >= max {
-			new_muscle.nodes.1 = rng.gen_range(0, max);
-		}
+		new_muscle.range_mut(max, rng);
 		new_muscle
 	}
 
+	/// Recalculates the muscle's length from the two nodes it connects, and
+	/// its contracted and extended lengths either side of it
+	pub fn update_length(&mut self, nodes: &Vec<Node>) {
+		self.contraction = self.contraction
+			.max(BOUNDS_MUSCLE_CONTRACTION.start)
+			.min(BOUNDS_MUSCLE_CONTRACTION.end);
+		self.extension = self.extension
+			.max(BOUNDS_MUSCLE_EXTENSION.start)
+			.min(BOUNDS_MUSCLE_EXTENSION.end);
+
+		self.len = nodes[self.nodes.0].distance(&nodes[self.nodes.1]);
+		self.len_min = self.len * self.contraction;
+		self.len_max = self.len * self.extension;
+	}
+
 	/// Return a muscle based on an existing one, only to make sure the nodes
 	/// are actually in range
 	pub fn range_mut(&mut self, max: usize, rng: &mut ThreadRng) {
@@ -316,13 +654,59 @@ impl Muscle {
 		if self.nodes.1 >= max {
 			self.nodes.1 = rng.gen_range(0, max);
 		}
+
+		// A muscle connecting a node to itself would have no length, so
+		// pick a different node for the other end
+		while self.nodes.0 == self.nodes.1 && max > 1 {
+			self.nodes.1 = rng.gen_range(0, max);
+		}
+
+		// Always make sure the lowest node index appears first
+		if self.nodes.1 < self.nodes.0 {
+			::std::mem::swap(&mut self.nodes.0, &mut self.nodes.1);
+		}
+	}
+}
+
+impl Objective {
+	/// Returns the creature's value for this objective, from its last
+	/// simulation
+	pub fn value(&self, creature: &Creature) -> f32 {
+		match *self {
+			Objective::Distance => creature.fitness,
+			Objective::Energy => -creature.energy,
+			Objective::Simplicity => {
+				-((creature.nodes.len() + creature.muscles.len()) as f32)
+			}
+		}
+	}
+
+	/// Returns the objective's name, for labelling exported data
+	pub fn title(&self) -> &'static str {
+		match *self {
+			Objective::Distance => "Distance",
+			Objective::Energy => "Energy",
+			Objective::Simplicity => "Simplicity"
+		}
+	}
+}
+
+impl Waveform {
+	/// Returns one of the waveforms at random
+	pub fn random(rng: &mut ThreadRng) -> Waveform {
+		match rng.gen_range(0, 3) {
+			0 => Waveform::Square,
+			1 => Waveform::Sine,
+			_ => Waveform::Triangle
+		}
 	}
 }
 
 #[cfg(test)]
 mod tests {
 	use rand;
-	use creature::{self, Creature, Node, Muscle, NodePair};
+	use creature::{self, Creature, Node, Muscle, NodePair, Waveform};
+	use physics::Simulation;
 
 	/// Create an empty creature, with no nodes or muscles
 	#[test]
@@ -392,8 +776,13 @@ mod tests {
 			len_min: 2.0,
 			len: 3.0,
 			len_max: 4.0,
+			contraction: 0.75,
+			extension: 1.2,
 			time_extended: 5,
 			time_contracted: 6,
+			phase: 7,
+			waveform: Waveform::Square,
+			neuron: None,
 			contracted: false
 		};
 
@@ -610,6 +999,150 @@ mod tests {
 				(muscle.time_contracted <
 				 creature::BOUNDS_MUSCLE_TIME_CONTRACTED.end)
 			);
+
+			assert!(
+				(muscle.phase >= creature::BOUNDS_MUSCLE_PHASE.start) &&
+				(muscle.phase < creature::BOUNDS_MUSCLE_PHASE.end)
+			);
+		}
+	}
+
+	/// Make sure a creature's lowest node is moved onto the ground, without
+	/// changing the shape of the creature or where its genome says it starts
+	#[test]
+	fn ground_start() {
+		let mut rng = rand::thread_rng();
+		let mut creature = Creature::new(&mut rng);
+		let start = creature.nodes.iter()
+			.map(|node| node.start_y)
+			.collect::<Vec<f32>>();
+		let height = creature.nodes[0].y - creature.nodes[1].y;
+
+		creature.ground_start(&Simulation::default());
+
+		let lowest = creature.nodes.iter().fold(0.0, |y, node| {
+			node.y.max(y)
+		});
+		assert_approx_eq!(lowest, creature::BOUNDS_NODE_Y.end);
+		assert_approx_eq!(
+			creature.nodes[0].y - creature.nodes[1].y, height
+		);
+		for (node, start_y) in creature.nodes.iter().zip(start) {
+			assert_eq!(node.start_y, start_y);
+		}
+	}
+
+	/// Make sure every muscle's contracted length is shorter than its resting
+	/// length, which is shorter than its extended length, even if its ratios
+	/// have been set out of bounds
+	#[test]
+	fn muscle_update_length() {
+		let mut rng = rand::thread_rng();
+		let mut creature = Creature::new(&mut rng);
+
+		for muscle in &creature.muscles {
+			assert!(muscle.len_min < muscle.len && muscle.len < muscle.len_max);
+		}
+
+		creature.muscles[0].contraction = 2.0;
+		creature.muscles[0].extension = 0.0;
+		creature.muscles[0].update_length(&creature.nodes);
+
+		let ref muscle = creature.muscles[0];
+		assert_eq!(muscle.contraction, creature::BOUNDS_MUSCLE_CONTRACTION.end);
+		assert_eq!(muscle.extension, creature::BOUNDS_MUSCLE_EXTENSION.start);
+		assert!(muscle.len_min < muscle.len && muscle.len < muscle.len_max);
+	}
+
+	/// Make sure a creature's genome hash ignores its simulation state and
+	/// fitness, but changes with any of its evolution properties
+	#[test]
+	fn genome_hash() {
+		let mut rng = rand::thread_rng();
+		let creature = Creature::new(&mut rng);
+		let mut moved = creature.clone();
+
+		moved.fitness = 100.0;
+		for node in &mut moved.nodes {
+			node.x += 10.0;
+			node.vx = 1.0;
+		}
+		assert_eq!(creature.genome_hash(), moved.genome_hash());
+
+		let mut changed = creature.clone();
+		changed.muscles[0].strength += 0.5;
+		assert!(creature.genome_hash() != changed.genome_hash());
+
+		let mut changed = creature.clone();
+		changed.nodes[0].friction *= 0.5;
+		assert!(creature.genome_hash() != changed.genome_hash());
+	}
+
+	/// Make sure setting a creature's genes to its own leaves it unchanged,
+	/// and that genes out of bounds are clamped back within them
+	#[test]
+	fn genes() {
+		let mut rng = rand::thread_rng();
+		let creature = Creature::new(&mut rng);
+		let genes = creature.genes();
+
+		assert_eq!(
+			genes.len(),
+			creature.nodes.len() * creature::GENES_NODE +
+			creature.muscles.len() * creature::GENES_MUSCLE
+		);
+
+		let mut copy = creature.clone();
+		copy.genes_set(&genes);
+		for (a, b) in genes.iter().zip(copy.genes().iter()) {
+			assert!((a - b).abs() < 0.0001);
+		}
+
+		copy.genes_set(&vec![2.0; genes.len()]);
+		for gene in copy.genes() {
+			assert!(gene >= 0.0 && gene <= 1.0);
+		}
+		assert!(copy.same_topology(&creature));
+		assert_eq!(
+			copy.muscles[0].strength,
+			creature::BOUNDS_MUSCLE_STRENGTH.end
+		);
+	}
+
+	/// Make sure a creature is fully compatible with itself, that distances
+	/// are the same both ways, and that an extra muscle counts as one more
+	#[test]
+	fn compatibility() {
+		let mut rng = rand::thread_rng();
+		let creature = Creature::new(&mut rng);
+		let other = Creature::new(&mut rng);
+
+		assert_approx_eq!(creature.compatibility(&creature), 0.0);
+		assert_approx_eq!(
+			creature.compatibility(&other),
+			other.compatibility(&creature)
+		);
+
+		let mut extra = creature.clone();
+		let muscle = Creature::add_muscle_random(&extra.nodes, &mut rng);
+		extra.muscles.push(muscle);
+		assert_approx_eq!(creature.compatibility(&extra), 1.0);
+	}
+
+	/// Add a neural controller to a random creature and make sure every muscle
+	/// ends up with its own neuron
+	#[test]
+	fn controller_add() {
+		let mut rng = rand::thread_rng();
+		let mut creature = Creature::new(&mut rng);
+
+		assert!(!creature.has_controller());
+
+		creature.controller_add(&mut rng);
+
+		assert!(creature.has_controller());
+		for muscle in &creature.muscles {
+			assert!(muscle.neuron.is_some());
 		}
 	}
 
@@ -4,6 +4,9 @@ extern crate rayon;
 #[cfg(test)] #[macro_use] extern crate assert_approx_eq;
 
 pub mod creature;
+pub mod controller;
 pub mod population;
 pub mod optimisationmethods;
 pub mod physics;
+pub mod batch;
+pub mod cache;
@@ -0,0 +1,482 @@
+use population::Population;
+use creature::Creature;
+use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
+use rand::{self, Rng, ThreadRng};
+use rand::distributions::normal::StandardNormal;
+use time;
+use rayon::prelude::*;
+
+/// Starting step size, as a fraction of each gene's bounds
+pub const SIGMA: f64 = 0.1;
+
+/// Most rounds of rotations used to find the covariance matrix's
+/// eigenvectors, which only runs out on a badly conditioned matrix
+const JACOBI_SWEEPS: usize = 50;
+
+/// A multivariate normal distribution which CMA-ES samples genes from, and
+/// adapts towards the fittest samples each generation
+pub struct Distribution {
+	pub mean: Vec<f64>,
+	pub sigma: f64,
+	pub covariance: Vec<Vec<f64>>,
+
+	// Eigen decomposition of the covariance matrix. Each eigenvector is a
+	// column, and the eigenvalues are the squared lengths of each axis.
+	eigenvectors: Vec<Vec<f64>>,
+	eigenvalues: Vec<f64>,
+
+	// Evolution paths of the covariance matrix and the step size
+	path_c: Vec<f64>,
+	path_sigma: Vec<f64>,
+
+	// Recombination weights of the fittest half of the samples
+	weights: Vec<f64>,
+	mu_eff: f64,
+
+	// Learning rates
+	c_c: f64,
+	c_sigma: f64,
+	c_1: f64,
+	c_mu: f64,
+	damping: f64,
+
+	// Expected length of a vector drawn from a standard normal distribution
+	chi_n: f64,
+	updates: i32
+}
+
+impl Distribution {
+	/// Creates a distribution around a mean which draws `lambda` samples
+	/// each generation, using the default learning rates
+	pub fn new(mean: Vec<f64>, sigma: f64, lambda: usize) -> Distribution {
+		let n = mean.len() as f64;
+		let mu = ::std::cmp::max(lambda / 2, 1);
+
+		let mut weights = (0 .. mu)
+			.map(|idx| (mu as f64 + 0.5).ln() - (idx as f64 + 1.0).ln())
+			.collect::<Vec<f64>>();
+		let sum = weights.iter().fold(0.0, |sum, weight| sum + weight);
+		for weight in &mut weights {
+			*weight /= sum;
+		}
+		let mu_eff = 1.0 /
+			weights.iter().fold(0.0, |sum, weight| sum + weight * weight);
+
+		let c_1 = 2.0 / ((n + 1.3) * (n + 1.3) + mu_eff);
+		let c_sigma = (mu_eff + 2.0) / (n + mu_eff + 5.0);
+
+		Distribution {
+			covariance: identity(mean.len()),
+			eigenvectors: identity(mean.len()),
+			eigenvalues: vec![1.0; mean.len()],
+			path_c: vec![0.0; mean.len()],
+			path_sigma: vec![0.0; mean.len()],
+			mean: mean,
+			sigma: sigma,
+			weights: weights,
+			mu_eff: mu_eff,
+			c_c: (4.0 + mu_eff / n) / (n + 4.0 + 2.0 * mu_eff / n),
+			c_sigma: c_sigma,
+			c_1: c_1,
+			c_mu: (1.0 - c_1).min(
+				2.0 * (mu_eff - 2.0 + 1.0 / mu_eff) /
+				((n + 2.0) * (n + 2.0) + mu_eff)
+			),
+			damping: 1.0 + c_sigma +
+				2.0 * (((mu_eff - 1.0) / (n + 1.0)).sqrt() - 1.0).max(0.0),
+			chi_n: n.sqrt() * (1.0 - 1.0 / (4.0 * n) + 1.0 / (21.0 * n * n)),
+			updates: 0
+		}
+	}
+
+	/// Draws a single sample from the distribution
+	pub fn sample(&self, rng: &mut ThreadRng) -> Vec<f64> {
+		let n = self.mean.len();
+		let z = (0 .. n).map(|idx| {
+			let StandardNormal(z) = rng.gen();
+			z * self.eigenvalues[idx].sqrt()
+		}).collect::<Vec<f64>>();
+
+		(0 .. n).map(|row| {
+			self.mean[row] + self.sigma * (0 .. n).fold(0.0, |sum, col| {
+				sum + self.eigenvectors[row][col] * z[col]
+			})
+		}).collect()
+	}
+
+	/// Moves the distribution towards the fittest samples of a generation,
+	/// which must be sorted from fittest to weakest
+	pub fn update(&mut self, samples: &[Vec<f64>]) {
+		let n = self.mean.len();
+		let mu = ::std::cmp::min(self.weights.len(), samples.len());
+		self.updates += 1;
+
+		// Steps from the old mean to each of the fittest samples
+		let steps = samples[.. mu].iter().map(|sample| {
+			(0 .. n).map(|idx| (sample[idx] - self.mean[idx]) / self.sigma)
+				.collect::<Vec<f64>>()
+		}).collect::<Vec<Vec<f64>>>();
+
+		let step = (0 .. n).map(|idx| {
+			(0 .. mu).fold(0.0, |sum, s| sum + self.weights[s] * steps[s][idx])
+		}).collect::<Vec<f64>>();
+
+		for idx in 0 .. n {
+			self.mean[idx] += self.sigma * step[idx];
+		}
+
+		// Step size path, measured in the distribution's own coordinates so
+		// its expected length doesn't depend on the covariance matrix
+		let whitened = self.inverse_sqrt(&step);
+		let scale_sigma =
+			(self.c_sigma * (2.0 - self.c_sigma) * self.mu_eff).sqrt();
+		for idx in 0 .. n {
+			self.path_sigma[idx] = (1.0 - self.c_sigma) * self.path_sigma[idx]
+				+ scale_sigma * whitened[idx];
+		}
+		let path_sigma_len = length(&self.path_sigma);
+
+		// Stop the covariance path growing while the step size is already
+		// increasing quickly
+		let decay = 1.0 - (1.0 - self.c_sigma).powi(2 * self.updates);
+		let h_sigma = path_sigma_len / decay.sqrt() / self.chi_n <
+			1.4 + 2.0 / (n as f64 + 1.0);
+		let h_sigma = if h_sigma { 1.0 } else { 0.0 };
+
+		let scale_c = (self.c_c * (2.0 - self.c_c) * self.mu_eff).sqrt();
+		for idx in 0 .. n {
+			self.path_c[idx] = (1.0 - self.c_c) * self.path_c[idx] +
+				h_sigma * scale_c * step[idx];
+		}
+
+		let keep = 1.0 - self.c_1 - self.c_mu;
+		let correction = (1.0 - h_sigma) * self.c_c * (2.0 - self.c_c);
+		for row in 0 .. n {
+			for col in 0 .. n {
+				let rank_one = self.path_c[row] * self.path_c[col] +
+					correction * self.covariance[row][col];
+				let rank_mu = (0 .. mu).fold(0.0, |sum, s| {
+					sum + self.weights[s] * steps[s][row] * steps[s][col]
+				});
+
+				self.covariance[row][col] = keep * self.covariance[row][col] +
+					self.c_1 * rank_one + self.c_mu * rank_mu;
+			}
+		}
+
+		self.sigma *= ((self.c_sigma / self.damping) *
+		               (path_sigma_len / self.chi_n - 1.0)).exp();
+
+		let (eigenvalues, eigenvectors) = eigen(&self.covariance);
+		self.eigenvalues = eigenvalues.iter()
+			.map(|value| value.max(1e-20))
+			.collect();
+		self.eigenvectors = eigenvectors;
+	}
+
+	/// Returns the average length of a step the distribution takes along
+	/// each gene, combining the step size with the covariance matrix
+	pub fn step_size(&self) -> f64 {
+		let n = self.mean.len();
+		let trace = (0 .. n).fold(0.0, |sum, idx| {
+			sum + self.covariance[idx][idx]
+		});
+
+		self.sigma * (trace / n as f64).sqrt()
+	}
+
+	/// Multiplies a vector by the inverse square root of the covariance
+	/// matrix
+	fn inverse_sqrt(&self, vector: &[f64]) -> Vec<f64> {
+		let n = vector.len();
+		let scaled = (0 .. n).map(|col| {
+			(0 .. n).fold(0.0, |sum, row| {
+				sum + self.eigenvectors[row][col] * vector[row]
+			}) / self.eigenvalues[col].sqrt()
+		}).collect::<Vec<f64>>();
+
+		(0 .. n).map(|row| {
+			(0 .. n).fold(0.0, |sum, col| {
+				sum + self.eigenvectors[row][col] * scaled[col]
+			})
+		}).collect()
+	}
+}
+
+/// Returns an identity matrix of the given size
+fn identity(n: usize) -> Vec<Vec<f64>> {
+	(0 .. n).map(|row| {
+		(0 .. n).map(|col| if row == col { 1.0 } else { 0.0 }).collect()
+	}).collect()
+}
+
+/// Returns the length of a vector
+fn length(vector: &[f64]) -> f64 {
+	vector.iter().fold(0.0, |sum, value| sum + value * value).sqrt()
+}
+
+/// Finds the eigenvalues and eigenvectors of a symmetric matrix using the
+/// Jacobi method, returning the eigenvectors as the columns of a matrix
+pub fn eigen(matrix: &[Vec<f64>]) -> (Vec<f64>, Vec<Vec<f64>>) {
+	let n = matrix.len();
+	let mut a = matrix.to_vec();
+	let mut vectors = identity(n);
+
+	for _ in 0 .. JACOBI_SWEEPS {
+		let off_diagonal = (0 .. n).fold(0.0, |sum, row| {
+			sum + (row + 1 .. n).fold(0.0, |sum, col| {
+				sum + a[row][col] * a[row][col]
+			})
+		});
+		if off_diagonal < 1e-30 { break; }
+
+		for p in 0 .. n {
+			for q in p + 1 .. n {
+				if a[p][q] == 0.0 { continue; }
+
+				// Rotate rows and columns p and q so a[p][q] becomes zero
+				let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+				let t = theta.signum() /
+					(theta.abs() + (theta * theta + 1.0).sqrt());
+				let c = 1.0 / (t * t + 1.0).sqrt();
+				let s = t * c;
+
+				for k in 0 .. n {
+					let (kp, kq) = (a[k][p], a[k][q]);
+					a[k][p] = c * kp - s * kq;
+					a[k][q] = s * kp + c * kq;
+				}
+				for k in 0 .. n {
+					let (pk, qk) = (a[p][k], a[q][k]);
+					a[p][k] = c * pk - s * qk;
+					a[q][k] = s * pk + c * qk;
+				}
+				for k in 0 .. n {
+					let (kp, kq) = (vectors[k][p], vectors[k][q]);
+					vectors[k][p] = c * kp - s * kq;
+					vectors[k][q] = s * kp + c * kq;
+				}
+			}
+		}
+	}
+
+	((0 .. n).map(|idx| a[idx][idx]).collect(), vectors)
+}
+
+/// CMA-ES (Covariance Matrix Adaptation Evolution Strategy). Keeps the body
+/// plan of a single creature and optimises only its continuous genes, by
+/// sampling every generation from a normal distribution which learns which
+/// genes should change together, and by how much.
+pub struct CmaEs {
+	pub data: OpMethodData,
+
+	// Creature whose nodes and muscles every sample is built on, which is
+	// the fittest creature when the distribution was set up
+	pub seed: Option<Creature>,
+	pub distribution: Option<Distribution>,
+
+	// Step size of the distribution at every generation
+	pub step_sizes: Vec<f32>
+}
+
+impl CmaEs {
+	pub fn new(population: Population, print: bool) -> Box<CmaEs> {
+		let mut cma = CmaEs {
+			data: OpMethodData::new(vec![population], "CMA".to_string(), print),
+			seed: None,
+			distribution: None,
+			step_sizes: Vec::new()
+		};
+		cma.reset();
+
+		Box::new(cma)
+	}
+
+	/// Builds and simulates a creature for each sample, keeping genes which
+	/// fall outside of their bounds at the edge so the distribution learns
+	/// from the genes which were actually used
+	fn offspring(&self, samples: &[Vec<f64>]) -> Vec<(Creature, Vec<f64>)> {
+		let seed = self.seed.as_ref().unwrap();
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+		let mut offspring = Vec::with_capacity(samples.len());
+
+		samples
+		.par_iter()
+		.map(|sample| {
+			let genes = sample.iter()
+				.map(|gene| gene.max(0.0).min(1.0))
+				.collect::<Vec<f64>>();
+
+			let mut child = seed.clone();
+			child.genes_set(
+				&genes.iter().map(|&gene| gene as f32).collect::<Vec<f32>>()
+			);
+
+			cache.simulate(&mut child, simulation);
+			(child, genes)
+		})
+		.collect_into(&mut offspring);
+
+		offspring
+	}
+}
+
+impl OptimisationMethod for CmaEs {
+	fn generation_single(&mut self) -> GenResult {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+
+		if self.data.print { println!(
+			"CMA - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}\tStep Size: {}",
+			self.data.gen,
+			self.data.generations[self.data.gen]
+			    .creatures[gen_size - 1]
+			    .fitness,
+			self.data.generations[self.data.gen].fitness_average(),
+			self.data.generations[self.data.gen].creatures[0].fitness,
+			self.step_sizes[self.data.gen]
+		); }
+
+		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		let samples = {
+			let distribution = self.distribution.as_ref().unwrap();
+			let mut rng = rand::thread_rng();
+			(0 .. gen_size)
+				.map(|_| distribution.sample(&mut rng))
+				.collect::<Vec<Vec<f64>>>()
+		};
+
+		let mut offspring = self.offspring(&samples);
+		offspring.sort_by(|a, b| b.0.cmp(&a.0));
+
+		let (creatures, samples): (Vec<Creature>, Vec<Vec<f64>>) =
+			offspring.into_iter().unzip();
+
+		let step_size = {
+			let distribution = self.distribution.as_mut().unwrap();
+			distribution.update(&samples);
+			distribution.step_size()
+		};
+
+		let mut new_population = Population::empty(gen_size);
+		new_population.creatures = creatures;
+
+		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		self.step_sizes.push(step_size as f32);
+		self.data.generations.push(new_population);
+		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
+		self.data.gen += 1;
+
+		Ok(())
+	}
+
+	fn creature_get (&mut self, gen: usize, idx: usize) -> &mut Creature {
+		&mut self.data.generations[gen].creatures[idx]
+	}
+
+	fn get_data_mut(&mut self) -> &mut OpMethodData {
+		&mut self.data
+	}
+
+	fn get_data(&self) -> &OpMethodData {
+		&self.data
+	}
+
+	fn step_size(&self, gen: usize) -> Option<f32> {
+		self.step_sizes.get(gen).cloned()
+	}
+
+	fn reset(&mut self) {
+		let gen = self.data.gen;
+		let seed = self.data.generations[gen].fittest().clone();
+		let mean = seed.genes().iter().map(|&gene| gene as f64).collect();
+		let gen_size = self.data.generations[gen].creatures.len();
+
+		self.seed = Some(seed);
+		self.distribution = Some(Distribution::new(mean, SIGMA, gen_size));
+		self.step_sizes.truncate(gen);
+		self.step_sizes.push(SIGMA as f32);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use population::Population;
+	use optimisationmethods::cma_es::{self, CmaEs, Distribution};
+	use optimisationmethods::OptimisationMethod;
+
+	/// Make sure the eigenvalues and eigenvectors of a small symmetric matrix
+	/// are found correctly
+	#[test]
+	fn eigen() {
+		let matrix = vec![vec![2.0, 1.0], vec![1.0, 2.0]];
+		let (mut values, vectors) = cma_es::eigen(&matrix);
+
+		for idx in 0 .. 2 {
+			// Each eigenvector is only stretched by the matrix
+			let x = vectors[0][idx];
+			let y = vectors[1][idx];
+			assert_approx_eq!(2.0 * x + y, values[idx] * x);
+			assert_approx_eq!(x + 2.0 * y, values[idx] * y);
+		}
+
+		values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+		assert_approx_eq!(values[0], 1.0);
+		assert_approx_eq!(values[1], 3.0);
+	}
+
+	/// Use the distribution on its own to find the bottom of a bowl, which
+	/// it should get very close to long before running out of generations
+	#[test]
+	fn distribution_sphere() {
+		let mut rng = rand::thread_rng();
+		let target = [0.3, -0.2, 0.5, 0.1, 0.0];
+		let mut distribution = Distribution::new(vec![1.0; 5], 0.5, 10);
+
+		let error = |sample: &Vec<f64>| {
+			sample.iter().zip(target.iter())
+				.fold(0.0, |sum, (x, t)| sum + (x - t) * (x - t))
+		};
+
+		for _ in 0 .. 200 {
+			let mut samples = (0 .. 10)
+				.map(|_| distribution.sample(&mut rng))
+				.collect::<Vec<Vec<f64>>>();
+			samples.sort_by(|a, b| error(a).partial_cmp(&error(b)).unwrap());
+			distribution.update(&samples);
+		}
+
+		assert!(error(&distribution.mean) < 1e-6);
+	}
+
+	/// Run 10 generations and make sure every creature keeps the body plan
+	/// of the seed, the average fitness has increased, and a step size was
+	/// recorded every generation
+	#[test]
+	fn fitness_10gens() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(100, &mut rng);
+		let mut cma = CmaEs::new(population, false);
+
+		for _ in 0 .. 10 {
+			let _ = cma.generation_single();
+		}
+
+		let seed = cma.seed.as_ref().unwrap();
+		for creature in &cma.get_data().generations[10].creatures {
+			assert!(creature.same_topology(seed));
+		}
+
+		let initial_fitness = cma.get_data().creature_get_average(0);
+		let final_fitness = cma.get_data().creature_get_average(10);
+		assert!(final_fitness > initial_fitness);
+
+		assert_eq!(cma.step_sizes.len(), 11);
+		assert!(cma.step_size(10).unwrap() > 0.0);
+	}
+}
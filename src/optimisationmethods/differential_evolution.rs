@@ -0,0 +1,241 @@
+use population::Population;
+use creature::Creature;
+use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
+use rand::{self, Rng, ThreadRng};
+use time;
+use rayon::prelude::*;
+
+pub const MUTABILITY_RATE: f32 = 0.05;
+
+/// How far along the difference between two creatures' genes a new
+/// creature is moved (F)
+pub const DIFFERENTIAL_WEIGHT: f32 = 0.5;
+
+/// Chance of each gene being taken from the mutant rather than the creature
+/// being replaced (CR)
+pub const CROSSOVER_RATE: f32 = 0.9;
+
+/// Which creature's genes the difference between two others is added to
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Variant {
+	/// DE/rand/1/bin: a random creature, which keeps the search spread out
+	Rand1Bin,
+
+	/// DE/best/1/bin: the fittest creature, which converges more quickly
+	Best1Bin
+}
+
+/// Differential Evolution. Every creature is challenged by a trial creature
+/// made by adding the difference between two other creatures' genes to a
+/// third, and is only replaced if the trial is at least as fit. Genes only
+/// line up between creatures with the same body plan, so creatures without
+/// enough others sharing theirs are mutated structurally instead.
+pub struct DifferentialEvolution {
+	pub data: OpMethodData,
+	pub variant: Variant,
+	pub weight: f32,
+	pub crossover_rate: f32
+}
+
+impl DifferentialEvolution {
+	pub fn new(
+		population: Population,
+		print: bool,
+		variant: Variant
+	) -> Box<DifferentialEvolution> {
+		Box::new(DifferentialEvolution {
+			data: OpMethodData::new(vec![population], "DE".to_string(), print),
+			variant: variant,
+			weight: DIFFERENTIAL_WEIGHT,
+			crossover_rate: CROSSOVER_RATE
+		})
+	}
+
+	/// Makes a trial creature to challenge the creature at `target`
+	fn trial(
+		&self,
+		creatures: &[Creature],
+		target: usize,
+		rng: &mut ThreadRng
+	) -> Creature {
+		let creature = &creatures[target];
+
+		// Creatures are sorted from fittest to weakest, so the first with
+		// the same body plan is also the fittest
+		let matching = (0 .. creatures.len())
+			.filter(|&idx| {
+				idx != target && creatures[idx].same_topology(creature)
+			})
+			.collect::<Vec<usize>>();
+
+		if matching.len() < 3 {
+			return OpMethodData::mutate(creature, rng, MUTABILITY_RATE);
+		}
+
+		let mut picked = rand::sample(rng, matching.iter().cloned(), 3);
+		rng.shuffle(&mut picked);
+
+		let base = match self.variant {
+			Variant::Rand1Bin => &creatures[picked[0]],
+			Variant::Best1Bin => {
+				let best = &creatures[matching[0]];
+				if best > creature { best } else { creature }
+			}
+		};
+
+		let base = base.genes();
+		let a = creatures[picked[1]].genes();
+		let b = creatures[picked[2]].genes();
+		let mut genes = creature.genes();
+
+		// Binomial crossover, always taking at least one gene from the mutant
+		let forced = rng.gen_range(0, genes.len());
+		for idx in 0 .. genes.len() {
+			if idx == forced || rng.gen::<f32>() < self.crossover_rate {
+				genes[idx] = base[idx] + self.weight * (a[idx] - b[idx]);
+			}
+		}
+
+		let mut trial = creature.clone();
+		trial.genes_set(&genes);
+		trial
+	}
+
+	/// Challenges every creature in the current generation with a trial,
+	/// keeping whichever of the two is fitter
+	fn offspring(&self) -> Vec<Creature> {
+		let creatures = &self.data.generations[self.data.gen].creatures;
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+		let mut offspring = Vec::with_capacity(creatures.len());
+
+		(0 .. creatures.len())
+		.into_par_iter()
+		.map(|target| {
+			let mut rng_new = rand::thread_rng();
+			let mut trial = self.trial(creatures, target, &mut rng_new);
+
+			cache.simulate(&mut trial, simulation);
+
+			if trial.fitness >= creatures[target].fitness {
+				trial
+			} else {
+				creatures[target].clone()
+			}
+		})
+		.collect_into(&mut offspring);
+
+		offspring
+	}
+}
+
+impl OptimisationMethod for DifferentialEvolution {
+	fn generation_single(&mut self) -> GenResult {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+		let mut new_population = Population::empty(gen_size);
+
+		if self.data.print { println!(
+			"DE - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}",
+			self.data.gen,
+			self.data.generations[self.data.gen]
+			    .creatures[gen_size - 1]
+			    .fitness,
+			self.data.generations[self.data.gen].fitness_average(),
+			self.data.generations[self.data.gen].creatures[0].fitness
+		); }
+
+		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		new_population.creatures = self.offspring();
+		new_population.sort_by_fittest();
+
+		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		self.data.generations.push(new_population);
+		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
+		self.data.gen += 1;
+
+		Ok(())
+	}
+
+	fn creature_get (&mut self, gen: usize, idx: usize) -> &mut Creature {
+		&mut self.data.generations[gen].creatures[idx]
+	}
+
+	fn get_data_mut(&mut self) -> &mut OpMethodData {
+		&mut self.data
+	}
+
+	fn get_data(&self) -> &OpMethodData {
+		&self.data
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use creature::Creature;
+	use population::Population;
+	use optimisationmethods::differential_evolution::{
+		DifferentialEvolution, Variant
+	};
+	use optimisationmethods::OptimisationMethod;
+
+	/// Run 10 generations of each variant and make sure the average fitness
+	/// never falls, as creatures are only ever replaced by fitter ones
+	#[test]
+	fn fitness_10gens() {
+		let mut rng = rand::thread_rng();
+
+		for &variant in &[Variant::Rand1Bin, Variant::Best1Bin] {
+			let population = Population::new(100, &mut rng);
+			let mut de = DifferentialEvolution::new(population, false, variant);
+
+			for gen in 0 .. 10 {
+				let _ = de.generation_single();
+				assert!(
+					de.get_data().creature_get_average(gen + 1) >=
+					de.get_data().creature_get_average(gen)
+				);
+			}
+
+			let initial_fitness = de.get_data().creature_get_average(0);
+			let final_fitness = de.get_data().creature_get_average(10);
+			assert!(final_fitness > initial_fitness);
+		}
+	}
+
+	/// Make a population sharing one body plan and make sure trial creatures
+	/// keep it, while a creature with a body plan of its own still gets a
+	/// trial through structural mutation
+	#[test]
+	fn trial_topology() {
+		let mut rng = rand::thread_rng();
+		let seed = Creature::new(&mut rng);
+		let mut population = Population::empty(10);
+		for _ in 0 .. 10 {
+			let mut creature = seed.clone();
+			let genes = creature.genes().iter()
+				.map(|_| rand::random::<f32>())
+				.collect::<Vec<f32>>();
+			creature.genes_set(&genes);
+			population.creatures.push(creature);
+		}
+
+		let de = DifferentialEvolution::new(
+			population.clone(),
+			false,
+			Variant::Rand1Bin
+		);
+		for target in 0 .. 10 {
+			let trial = de.trial(&population.creatures, target, &mut rng);
+			assert!(trial.same_topology(&seed));
+		}
+
+		let lonely = vec![Creature::new(&mut rng), Creature::new(&mut rng)];
+		let trial = de.trial(&lonely, 0, &mut rng);
+		assert!(!trial.nodes.is_empty());
+	}
+}
@@ -0,0 +1,266 @@
+use population::Population;
+use creature::Creature;
+use physics;
+use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
+use rand::{self, Rng};
+use rand::distributions::normal::StandardNormal;
+use std::cmp;
+use std::ops::Range;
+use time;
+use rayon::prelude::*;
+
+/// Step size every creature in the starting population mutates with
+pub const STEP_SIZE: f32 = 0.05;
+
+/// Smallest and largest step sizes a creature can adapt to
+pub const BOUNDS_STEP_SIZE: Range<f32> = 0.005 .. 0.5;
+
+/// How far a step size can change each time it's mutated (tau)
+pub const LEARNING_RATE: f32 = 0.3;
+
+/// Which creatures the next generation's parents are chosen from
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Selection {
+	/// (μ + λ): the parents compete with their children to survive
+	Plus,
+
+	/// (μ, λ): only the children survive, so parents are always replaced
+	Comma
+}
+
+/// Evolution Strategies. The fittest μ creatures of each generation are
+/// mutated into λ children, where every creature carries its own step size
+/// used to mutate it. A child's step size is mutated before the child is, so
+/// step sizes which produce fitter children survive along with them.
+pub struct EvolutionStrategies {
+	pub data: OpMethodData,
+	pub selection: Selection,
+
+	// Number of the fittest creatures children are mutated from (μ)
+	pub parents: usize,
+
+	// Step size of each creature in the current generation, in the same
+	// order as its creatures
+	pub steps: Vec<f32>,
+
+	// Average step size of every generation
+	pub step_average: Vec<f32>
+}
+
+impl EvolutionStrategies {
+	pub fn new(
+		population: Population,
+		print: bool,
+		selection: Selection
+	) -> Box<EvolutionStrategies> {
+		let gen_size = population.creatures.len();
+		let title = match selection {
+			Selection::Plus => "ES Plus",
+			Selection::Comma => "ES Comma"
+		};
+
+		Box::new(EvolutionStrategies {
+			data: OpMethodData::new(vec![population], title.to_string(), print),
+			selection: selection,
+			parents: cmp::max(gen_size / 4, 1),
+			steps: vec![STEP_SIZE; gen_size],
+			step_average: vec![STEP_SIZE]
+		})
+	}
+
+	/// Mutates a child, along with its step size, from a random parent for
+	/// every creature in the generation
+	fn offspring(&self, gen_size: usize) -> Vec<(Creature, f32)> {
+		let parents = &self.data.generations[self.data.gen].creatures;
+		let steps = &self.steps;
+		let mu = cmp::min(self.parents, parents.len());
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+		let mut offspring = Vec::with_capacity(gen_size);
+
+		(0 .. gen_size)
+		.into_par_iter()
+		.map(|_| {
+			let mut rng_new = rand::thread_rng();
+			let parent = rng_new.gen_range(0, mu);
+
+			// Log-normal self-adaptation, so a step size is as likely to
+			// halve as it is to double
+			let StandardNormal(n) = rng_new.gen();
+			let step = physics::clamp(
+				steps[parent] * (LEARNING_RATE * n as f32).exp(),
+				BOUNDS_STEP_SIZE
+			);
+
+			let mut child = OpMethodData::mutate(
+				&parents[parent],
+				&mut rng_new,
+				step
+			);
+
+			cache.simulate(&mut child, simulation);
+			(child, step)
+		})
+		.collect_into(&mut offspring);
+
+		offspring
+	}
+}
+
+impl OptimisationMethod for EvolutionStrategies {
+	fn generation_single(&mut self) -> GenResult {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+
+		if self.data.print { println!(
+			"{} - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}\tStep Size: {}",
+			self.data.title,
+			self.data.gen,
+			self.data.generations[self.data.gen]
+			    .creatures[gen_size - 1]
+			    .fitness,
+			self.data.generations[self.data.gen].fitness_average(),
+			self.data.generations[self.data.gen].creatures[0].fitness,
+			self.step_average[self.data.gen]
+		); }
+
+		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		let mut offspring = self.offspring(gen_size);
+
+		// With plus selection the parents carry on into the next generation
+		// unless enough of their children are fitter than them
+		if self.selection == Selection::Plus {
+			let mu = cmp::min(self.parents, gen_size);
+			let parents = &self.data.generations[self.data.gen].creatures;
+			for idx in 0 .. mu {
+				offspring.push((parents[idx].clone(), self.steps[idx]));
+			}
+		}
+
+		offspring.sort_by(|a, b| b.0.cmp(&a.0));
+		offspring.truncate(gen_size);
+
+		let (creatures, steps): (Vec<Creature>, Vec<f32>) =
+			offspring.into_iter().unzip();
+
+		let mut new_population = Population::empty(gen_size);
+		new_population.creatures = creatures;
+
+		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		self.step_average.push(
+			steps.iter().fold(0.0, |sum, step| sum + step) / gen_size as f32
+		);
+		self.steps = steps;
+
+		self.data.generations.push(new_population);
+		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
+		self.data.gen += 1;
+
+		Ok(())
+	}
+
+	fn creature_get (&mut self, gen: usize, idx: usize) -> &mut Creature {
+		&mut self.data.generations[gen].creatures[idx]
+	}
+
+	fn get_data_mut(&mut self) -> &mut OpMethodData {
+		&mut self.data
+	}
+
+	fn get_data(&self) -> &OpMethodData {
+		&self.data
+	}
+
+	fn step_size(&self, gen: usize) -> Option<f32> {
+		self.step_average.get(gen).cloned()
+	}
+
+	// Re-simulating the generation sorts its creatures again, so there's no
+	// telling which step size went with which creature. Every creature
+	// starts again from the generation's average step size instead.
+	fn reset(&mut self) {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+		self.steps = vec![self.step_average[self.data.gen]; gen_size];
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use physics::Simulation;
+	use population::Population;
+	use optimisationmethods::evolution_strategies::{self, Selection};
+	use optimisationmethods::evolution_strategies::EvolutionStrategies;
+	use optimisationmethods::OptimisationMethod;
+
+	/// Run 10 generations with each kind of selection and make sure the
+	/// average fitness has increased
+	#[test]
+	fn fitness_10gens() {
+		let mut rng = rand::thread_rng();
+
+		for &selection in &[Selection::Plus, Selection::Comma] {
+			let population = Population::new(100, &mut rng);
+			let mut es = EvolutionStrategies::new(population, false, selection);
+
+			for _ in 0 .. 10 {
+				let _ = es.generation_single();
+			}
+
+			let initial_fitness = es.get_data().creature_get_average(0);
+			let final_fitness = es.get_data().creature_get_average(10);
+
+			assert!(final_fitness > initial_fitness);
+		}
+	}
+
+	/// Make sure plus selection never loses its fittest creature, and every
+	/// creature keeps a step size within bounds which has adapted away from
+	/// the one it started with
+	#[test]
+	fn step_sizes() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(100, &mut rng);
+		let mut es =
+			EvolutionStrategies::new(population, false, Selection::Plus);
+
+		for gen in 0 .. 10 {
+			let _ = es.generation_single();
+			assert!(
+				es.get_data().creature_get_fittest(gen + 1).fitness >=
+				es.get_data().creature_get_fittest(gen).fitness
+			);
+		}
+
+		assert_eq!(es.steps.len(), 100);
+		assert_eq!(es.step_average.len(), 11);
+		assert!(es.steps.iter().any(|&step| {
+			step != evolution_strategies::STEP_SIZE
+		}));
+		for &step in &es.steps {
+			assert!(step >= evolution_strategies::BOUNDS_STEP_SIZE.start);
+			assert!(step <= evolution_strategies::BOUNDS_STEP_SIZE.end);
+		}
+	}
+	/// Change the simulation part way through, making sure every creature
+	/// in the re-sorted generation still has a step size
+	#[test]
+	fn simulation_set() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(10, &mut rng);
+		let mut es =
+			EvolutionStrategies::new(population, false, Selection::Comma);
+		let _ = es.generation_single();
+		let _ = es.generation_single();
+
+		es.simulation_set(Simulation::water());
+
+		assert_eq!(es.steps.len(), 10);
+		for &step in &es.steps {
+			assert_eq!(step, es.step_average[2]);
+		}
+	}
+}
@@ -1,7 +1,8 @@
 use population::Population;
 use creature::{Creature, Node, Muscle};
+use physics::Simulation;
+use cache::FitnessCache;
 use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
-use physics;
 use rand::{self, Rng, ThreadRng};
 use time;
 use rayon::prelude::*;
@@ -19,27 +20,84 @@ impl GeneticAlgorithm {
 		})
 	}
 
-	fn selection (&self, rng: &mut ThreadRng) -> &Creature {
+	/// Returns the fittest of a few creatures picked at random
+	pub fn tournament<'a> (
+		creatures: &'a [Creature],
+		rng: &mut ThreadRng
+	) -> &'a Creature {
 		// Tournament Selection
 		let selection_size = 3;
 
 		let mut selection: Vec<&Creature> = Vec::with_capacity(selection_size);
 
 		for _ in 0 .. selection_size {
-			let idx = rng.gen_range(
-				0, self.data.generations[self.data.gen].creatures.len()
-			);
-
-			selection.push(
-				&self.data.generations[self.data.gen].creatures[idx]
-			);
+			let idx = rng.gen_range(0, creatures.len());
+			selection.push(&creatures[idx]);
 		}
 
 		selection.iter().max().unwrap()
 	}
 
+	/// Returns the creature with the highest score out of a few picked at
+	/// random, for methods which select by something other than fitness
+	pub fn tournament_scores<'a> (
+		creatures: &'a [Creature],
+		scores: &[f32],
+		rng: &mut ThreadRng
+	) -> &'a Creature {
+		let selection_size = 3;
+		let mut best = rng.gen_range(0, creatures.len());
+
+		for _ in 1 .. selection_size {
+			let idx = rng.gen_range(0, creatures.len());
+			if scores[idx] > scores[best] {
+				best = idx;
+			}
+		}
+
+		&creatures[best]
+	}
+
+	/// Breeds a child from two parents chosen by tournaments among the given
+	/// creatures, then mutates and simulates it
+	pub fn breed(
+		creatures: &[Creature],
+		simulation: &Simulation,
+		cache: &FitnessCache,
+		rng: &mut ThreadRng
+	) -> Creature {
+		// Select two creatures through tournaments
+		let creature_a = GeneticAlgorithm::tournament(creatures, rng);
+		let creature_b = GeneticAlgorithm::tournament(creatures, rng);
+
+		GeneticAlgorithm::child(creature_a, creature_b, simulation, cache, rng)
+	}
+
+	/// Breeds a child from two parents, then mutates and simulates it
+	pub fn child(
+		creature_a: &Creature,
+		creature_b: &Creature,
+		simulation: &Simulation,
+		cache: &FitnessCache,
+		rng: &mut ThreadRng
+	) -> Creature {
+		// Breed them to make a new child
+		let child = GeneticAlgorithm::crossover(creature_a, creature_b, rng);
+
+		// Mutate the child ever so slightly so it's not just the same
+		// as the parents
+		let mut child = OpMethodData::mutate(
+			&child,          // Creature to mutate
+			rng,             // The RNG thread to create random nums with
+			MUTABILITY_RATE
+		);
+
+		cache.simulate(&mut child, simulation);
+		child
+	}
+
 	/// Takes two parent creatures and returns a child creature.
-	fn crossover (
+	pub fn crossover (
 		creature_a: &Creature,
 		creature_b: &Creature,
 		rng: &mut ThreadRng
@@ -103,6 +161,18 @@ impl GeneticAlgorithm {
 			}
 		}).collect::<Vec<Muscle>>();
 
+		// The child's nodes may be in different places to the parent the
+		// muscle came from, so their lengths need recalculating
+		for muscle in &mut child.muscles {
+			muscle.update_length(&child.nodes);
+		}
+
+		// Muscles taken from a parent without a neural controller still need
+		// a neuron if the child is going to be controlled by one
+		if creature_a.has_controller() || creature_b.has_controller() {
+			child.controller_add(rng);
+		}
+
 		// Finally, return the child we have bred from two parents.
 		child
 	}
@@ -126,37 +196,24 @@ impl OptimisationMethod for GeneticAlgorithm {
 		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
 
 		// Loop until we reach the size of a population
-		(0 .. gen_size)
-		.into_par_iter()
-		.map(|_| {
-			let mut rng_new = rand::thread_rng();
-
-			// Select two creatures through tournaments
-			let creature_a = self.selection(&mut rng_new);
-			let creature_b = self.selection(&mut rng_new);
-
-			// Breed them to make a new child
-			let mut child = GeneticAlgorithm::crossover(
-				creature_a,
-				creature_b,
-				&mut rng_new
-			);
-
-			// Mutate the child ever so slightly so it's not just the same
-			// as the parents
-			child = OpMethodData::mutate(
-				&child,          // Creature to mutate
-				&mut rng_new,    // The RNG thread to create random nums with
-				MUTABILITY_RATE
-			);
-
-			physics::full_simulation_creature(&mut child);
-
-			// Finally add the child to the population of child creatures
-			//new_population.creatures.push(child);
-			child
-		})
-		.collect_into(&mut new_population.creatures);
+		{
+			let creatures = &self.data.generations[self.data.gen].creatures;
+			let simulation = &self.data.simulation;
+			let cache = &self.data.cache;
+
+			(0 .. gen_size)
+			.into_par_iter()
+			.map(|_| {
+				let mut rng_new = rand::thread_rng();
+				GeneticAlgorithm::breed(
+					creatures,
+					simulation,
+					cache,
+					&mut rng_new
+				)
+			})
+			.collect_into(&mut new_population.creatures);
+		}
 
 		// After generating a new population we must calculate the fitness
 		// of each creature in a population.
@@ -170,6 +227,8 @@ impl OptimisationMethod for GeneticAlgorithm {
 		// method, and increase the generation number
 		self.data.generations.push(new_population);
 		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
 		self.data.gen += 1;
 
 		Ok(())
@@ -230,11 +289,12 @@ mod tests {
 
 		// Initalise the genetic algorithm with the population
 		let ga = GeneticAlgorithm::new(population, false);
+		let creatures = &ga.get_data().generations[0].creatures;
 		let fitness_weakest = ga.get_data().creature_get_weakest(0).fitness;
 
 		// Test it 10 times to make sure it definitely works properly
 		for _ in 0 .. 10 {
-			let selection = ga.selection(&mut rng);
+			let selection = GeneticAlgorithm::tournament(creatures, &mut rng);
 			assert!(selection.fitness > fitness_weakest);
 		}
 	}
@@ -246,10 +306,11 @@ mod tests {
 		let mut rng = rand::thread_rng();
 		let population = Population::new(500, &mut rng);
 		let ga = GeneticAlgorithm::new(population, false);
+		let creatures = &ga.get_data().generations[0].creatures;
 
 		for _ in 0 .. 10 {
-			let parent_a = ga.selection(&mut rng);
-			let parent_b = ga.selection(&mut rng);
+			let parent_a = GeneticAlgorithm::tournament(creatures, &mut rng);
+			let parent_b = GeneticAlgorithm::tournament(creatures, &mut rng);
 
 			let child = GeneticAlgorithm::crossover(
 				parent_a,
@@ -265,6 +326,11 @@ mod tests {
 				child.muscles.len() == parent_a.muscles.len() ||
 				child.muscles.len() == parent_b.muscles.len()
 			);
+			for muscle in &child.muscles {
+				assert!(
+					muscle.len_min < muscle.len && muscle.len < muscle.len_max
+				);
+			}
 		}
 	}
 }
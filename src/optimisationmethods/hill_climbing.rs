@@ -1,78 +1,229 @@
 use population::Population;
 use creature::Creature;
+use physics;
 use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
-use rand;
+use rand::{self, ThreadRng};
+use std::ops::Range;
 use time;
-use physics;
 use rayon::prelude::*;
 
 pub const CLIMB_ATTEMPTS: usize = 4;
 pub const MUTABILITY_RATE: f32 = 0.05;
 
+/// Smallest and largest step sizes a climber can adapt to
+pub const BOUNDS_STEP_SIZE: Range<f32> = 0.005 .. 0.5;
+
+/// How much an adaptive climber's step size grows after it improves, and
+/// shrinks after it doesn't
+pub const STEP_GROW: f32 = 1.5;
+pub const STEP_SHRINK: f32 = 0.8;
+
+/// How a climber chooses which neighbour to move to
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Strategy {
+	/// Simulates every neighbour and moves to the fittest, if it improves
+	SteepestAscent,
+
+	/// Moves to the first neighbour which improves, simulating fewer
+	FirstImprovement
+}
+
+/// The state each creature climbs with, kept alongside it between
+/// generations
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Climber {
+	pub step: f32,
+
+	// Generations since the creature last improved
+	pub stagnant: usize
+}
+
+impl Climber {
+	pub fn new() -> Climber {
+		Climber {
+			step: MUTABILITY_RATE,
+			stagnant: 0
+		}
+	}
+}
+
+/// Hill Climbing. Every creature climbs on its own, moving to a mutated
+/// neighbour whenever one is fitter. Climbers can optionally adapt how far
+/// they mutate, and start again from a new random creature once they've
+/// been stuck on a local optimum for too long.
 pub struct HillClimbing {
-	pub data: OpMethodData
+	pub data: OpMethodData,
+	pub strategy: Strategy,
+
+	// Most neighbours simulated for each creature every generation
+	pub neighbours: usize,
+
+	// Number of generations without improving before a climber restarts
+	pub restart: Option<usize>,
+
+	// Whether step sizes grow after improving and shrink otherwise
+	pub adaptive: bool,
+
+	// State of each creature in the current generation, in the same order
+	// as its creatures
+	pub climbers: Vec<Climber>,
+
+	// Average step size and number of restarts of every generation
+	pub step_average: Vec<f32>,
+	pub restarts: Vec<usize>,
+
+	// Whether restarted creatures are given a neural controller, to match
+	// the starting population
+	pub controllers: bool
 }
 
 impl HillClimbing {
-	pub fn new(population: Population, print: bool) -> Box<HillClimbing> {
+	pub fn new(
+		population: Population,
+		print: bool,
+		strategy: Strategy
+	) -> Box<HillClimbing> {
+		let gen_size = population.creatures.len();
+		let controllers = population.creatures.iter()
+			.any(|creature| creature.has_controller());
+		let title = match strategy {
+			Strategy::SteepestAscent => "HC",
+			Strategy::FirstImprovement => "HC First"
+		};
+
 		Box::new(HillClimbing {
-			data: OpMethodData::new(vec![population], "HC".to_string(), print)
+			data: OpMethodData::new(vec![population], title.to_string(), print),
+			strategy: strategy,
+			neighbours: CLIMB_ATTEMPTS,
+			restart: None,
+			adaptive: false,
+			climbers: vec![Climber::new(); gen_size],
+			step_average: vec![MUTABILITY_RATE],
+			restarts: vec![0],
+			controllers: controllers
+		})
+	}
+
+	/// Climbs a single step from the given creature, returning where it ends
+	/// up along with its updated state
+	pub fn climb(
+		&self,
+		creature: &Creature,
+		climber: Climber,
+		rng: &mut ThreadRng
+	) -> (Creature, Climber) {
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+		let mut climber = climber;
+
+		if self.restart.map_or(false, |limit| climber.stagnant >= limit) {
+			let mut restarted = Creature::new(rng);
+			if self.controllers {
+				restarted.controller_add(rng);
+			}
+
+			cache.simulate(&mut restarted, simulation);
+			return (restarted, Climber::new());
+		}
+
+		let mut fittest: Option<Creature> = None;
+		for _ in 0 .. self.neighbours {
+			let mut neighbour =
+				OpMethodData::mutate(creature, rng, climber.step);
+			cache.simulate(&mut neighbour, simulation);
+
+			if neighbour.fitness > fittest.as_ref()
+				.map_or(creature.fitness, |fittest| fittest.fitness)
+			{
+				fittest = Some(neighbour);
+
+				if self.strategy == Strategy::FirstImprovement {
+					break;
+				}
+			}
+		}
+
+		let improved = fittest.is_some();
+		if self.adaptive {
+			let scale = if improved { STEP_GROW } else { STEP_SHRINK };
+			climber.step =
+				physics::clamp(climber.step * scale, BOUNDS_STEP_SIZE);
+		}
+		climber.stagnant = if improved { 0 } else { climber.stagnant + 1 };
+
+		(fittest.unwrap_or_else(|| creature.clone()), climber)
+	}
+
+	/// Climbs every creature in the current generation a single step
+	fn offspring(&self) -> Vec<(Creature, Climber)> {
+		let creatures = &self.data.generations[self.data.gen].creatures;
+		let mut offspring = Vec::with_capacity(creatures.len());
+
+		creatures
+		.par_iter()
+		.zip(self.climbers.par_iter())
+		.map(|(creature, &climber)| {
+			let mut rng_new = rand::thread_rng();
+			self.climb(creature, climber, &mut rng_new)
 		})
+		.collect_into(&mut offspring);
+
+		offspring
 	}
 }
 
 impl OptimisationMethod for HillClimbing {
 	fn generation_single(&mut self) -> GenResult {
 		let gen_size = self.data.generations[self.data.gen].creatures.len();
-		let mut new_population = Population::empty(gen_size);
 
 		if self.data.print { println!(
-			"HC - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}",
+			"{} - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}\tRestarts: {}",
+			self.data.title,
 			self.data.gen,
 			self.data.generations[self.data.gen]
 			    .creatures[gen_size - 1]
 			    .fitness,
 			self.data.generations[self.data.gen].fitness_average(),
-			self.data.generations[self.data.gen].creatures[0].fitness
+			self.data.generations[self.data.gen].creatures[0].fitness,
+			self.restarts[self.data.gen]
 		); }
 
 		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
 
-		self.data.generations[self.data.gen].creatures
-		.par_iter_mut()
-		.map(|creature| {
-			let mut new_creatures = Vec::with_capacity(CLIMB_ATTEMPTS);
-			let mut rng_new = rand::thread_rng();
-			for _ in 0 .. CLIMB_ATTEMPTS {
-				let mut new_creature = OpMethodData::mutate(
-					creature,
-					&mut rng_new,
-					MUTABILITY_RATE
-				);
-
-				physics::full_simulation_creature(&mut new_creature);
-				new_creatures.push(new_creature);
-			}
+		// Restarted climbers are the only ones whose state is back to new
+		// without having improved
+		let restart = self.restart;
+		let restarts = self.climbers.iter()
+			.filter(|climber| {
+				restart.map_or(false, |limit| climber.stagnant >= limit)
+			})
+			.count();
 
-			new_creatures.sort_by(|a,b| b.cmp(a));
+		let mut offspring = self.offspring();
+		offspring.sort_by(|a, b| b.0.cmp(&a.0));
 
-			if new_creatures[0].fitness > creature.fitness {
-				new_creatures[0].clone()
-			} else {
-				creature.clone()
-			}
-		})
-		.collect_into(&mut new_population.creatures);
+		let (creatures, climbers): (Vec<Creature>, Vec<Climber>) =
+			offspring.into_iter().unzip();
 
-		new_population.sort_by_fittest();
+		let mut new_population = Population::empty(gen_size);
+		new_population.creatures = creatures;
 
 		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
 
+		self.step_average.push(
+			climbers.iter().fold(0.0, |sum, climber| sum + climber.step) /
+			gen_size as f32
+		);
+		self.restarts.push(restarts);
+		self.climbers = climbers;
+
 		// After having created the new population, sort the current
 		// population by fittest, add the new population to the optimisation
 		// method, and increase the generation number
 		self.data.generations.push(new_population);
 		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
 		self.data.gen += 1;
 
 		Ok(())
@@ -89,13 +240,34 @@ impl OptimisationMethod for HillClimbing {
 	fn get_data(&self) -> &OpMethodData {
 		&self.data
 	}
+
+	fn step_size(&self, gen: usize) -> Option<f32> {
+		if self.adaptive {
+			self.step_average.get(gen).cloned()
+		} else {
+			None
+		}
+	}
+
+	// Re-simulating the generation sorts its creatures again, so every
+	// climber starts again from the generation's average step size, with
+	// nothing counted against it from the old simulation
+	fn reset(&mut self) {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+		let climber = Climber {
+			step: self.step_average[self.data.gen],
+			stagnant: 0
+		};
+		self.climbers = vec![climber; gen_size];
+	}
 }
 
 #[cfg(test)]
 mod tests {
 	use rand;
+	use physics::Simulation;
 	use population::Population;
-	use optimisationmethods::hill_climbing::HillClimbing;
+	use optimisationmethods::hill_climbing::{self, HillClimbing, Strategy};
 	use optimisationmethods::OptimisationMethod;
 
 	/// Run the generation function 10 times and make sure the average
@@ -110,7 +282,8 @@ mod tests {
 			let population = Population::new(100, &mut rng);
 
 			// Initalise the genetic algorithm with the population
-			let mut hc = HillClimbing::new(population, false);
+			let mut hc =
+				HillClimbing::new(population, false, Strategy::SteepestAscent);
 
 			for _ in 0 .. 10 {
 				let _ = hc.generation_single();
@@ -122,5 +295,73 @@ mod tests {
 			assert!(final_fitness > initial_fitness);
 		}
 	}
-}
 
+	/// Run 10 generations climbing to the first improvement with adaptive
+	/// step sizes, and make sure fitness increased and every step size
+	/// stayed within bounds
+	#[test]
+	fn first_improvement_adaptive() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(100, &mut rng);
+		let mut hc =
+			HillClimbing::new(population, false, Strategy::FirstImprovement);
+		hc.adaptive = true;
+
+		for _ in 0 .. 10 {
+			let _ = hc.generation_single();
+		}
+
+		let initial_fitness = hc.get_data().creature_get_average(0);
+		let final_fitness = hc.get_data().creature_get_average(10);
+		assert!(final_fitness > initial_fitness);
+
+		assert_eq!(hc.step_average.len(), 11);
+		assert!(hc.step_size(10).is_some());
+		for climber in &hc.climbers {
+			assert!(climber.step >= hill_climbing::BOUNDS_STEP_SIZE.start);
+			assert!(climber.step <= hill_climbing::BOUNDS_STEP_SIZE.end);
+		}
+	}
+
+	/// Climbers with no neighbours can never improve, so make sure every one
+	/// of them restarts once it's been stuck for the given number of
+	/// generations
+	#[test]
+	fn random_restart() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(100, &mut rng);
+		let mut hc =
+			HillClimbing::new(population, false, Strategy::SteepestAscent);
+		hc.neighbours = 0;
+		hc.restart = Some(2);
+
+		for _ in 0 .. 2 {
+			let _ = hc.generation_single();
+		}
+		assert!(hc.climbers.iter().all(|climber| climber.stagnant == 2));
+
+		let _ = hc.generation_single();
+		assert_eq!(hc.restarts, vec![0, 0, 0, 100]);
+		assert!(hc.climbers.iter().all(|climber| climber.stagnant == 0));
+	}
+	/// Change the simulation part way through, making sure every creature
+	/// in the re-sorted generation climbs again from a fresh start
+	#[test]
+	fn simulation_set() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(10, &mut rng);
+		let mut hc =
+			HillClimbing::new(population, false, Strategy::SteepestAscent);
+		hc.neighbours = 0;
+		hc.adaptive = true;
+		let _ = hc.generation_single();
+
+		hc.simulation_set(Simulation::water());
+
+		assert_eq!(hc.climbers.len(), 10);
+		for climber in &hc.climbers {
+			assert_eq!(climber.step, hc.step_average[1]);
+			assert_eq!(climber.stagnant, 0);
+		}
+	}
+}
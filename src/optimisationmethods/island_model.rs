@@ -0,0 +1,282 @@
+use population::Population;
+use creature::Creature;
+use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
+use optimisationmethods::GroupStats;
+use optimisationmethods::genetic_algorithm::GeneticAlgorithm;
+use rand;
+use time;
+use rayon::prelude::*;
+
+/// Number of sub-populations the population is split into
+pub const ISLANDS: usize = 4;
+
+/// Number of generations between each migration
+pub const MIGRATION_INTERVAL: usize = 5;
+
+/// Number of each island's fittest creatures sent to every island it's
+/// connected to when migrating
+pub const MIGRANTS: usize = 2;
+
+/// Which islands creatures migrate to
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Topology {
+	/// Each island only sends creatures to the next one along
+	Ring,
+
+	/// Each island sends creatures to every other island
+	FullyConnected
+}
+
+/// Island Model Genetic Algorithm. The population is split into islands
+/// which each evolve separately with the genetic algorithm, occasionally
+/// swapping their fittest creatures, so different body plans can be kept
+/// alive on different islands rather than one taking over.
+pub struct IslandModel {
+	pub data: OpMethodData,
+	pub topology: Topology,
+	pub migrants: usize,
+
+	// Number of generations between each migration, where 0 means the
+	// islands never swap creatures
+	pub interval: usize,
+
+	// Number of islands, and the creatures currently on each of them
+	pub island_count: usize,
+	pub islands: Vec<Population>
+}
+
+impl IslandModel {
+	pub fn new(
+		population: Population,
+		print: bool,
+		topology: Topology,
+		island_count: usize
+	) -> Box<IslandModel> {
+		let mut im = IslandModel {
+			data: OpMethodData::new(vec![population], "IM".to_string(), print),
+			topology: topology,
+			migrants: MIGRANTS,
+			interval: MIGRATION_INTERVAL,
+			island_count: island_count,
+			islands: Vec::new()
+		};
+		im.reset();
+
+		Box::new(im)
+	}
+
+	/// Breeds a new generation on every island, all in parallel
+	fn offspring(&self) -> Vec<Population> {
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+		let mut islands = Vec::with_capacity(self.islands.len());
+
+		self.islands
+		.par_iter()
+		.map(|island| {
+			let creatures = &island.creatures;
+			let mut new_island = Population::empty(creatures.len());
+
+			(0 .. creatures.len())
+			.into_par_iter()
+			.map(|_| {
+				let mut rng = rand::thread_rng();
+				GeneticAlgorithm::breed(creatures, simulation, cache, &mut rng)
+			})
+			.collect_into(&mut new_island.creatures);
+
+			new_island.sort_by_fittest();
+			new_island
+		})
+		.collect_into(&mut islands);
+
+		islands
+	}
+
+	/// Copies the fittest creatures of each island to the islands it's
+	/// connected to, where they replace the weakest creatures
+	pub fn migrate(&self, islands: &mut Vec<Population>) {
+		let count = islands.len();
+		if count < 2 { return }
+
+		let emigrants = islands.iter()
+			.map(|island| {
+				island.creatures.iter()
+					.take(self.migrants)
+					.cloned()
+					.collect::<Vec<Creature>>()
+			})
+			.collect::<Vec<Vec<Creature>>>();
+
+		for (idx, island) in islands.iter_mut().enumerate() {
+			let mut immigrants = match self.topology {
+				Topology::Ring => emigrants[(idx + count - 1) % count].clone(),
+				Topology::FullyConnected => (0 .. count)
+					.filter(|&from| from != idx)
+					.flat_map(|from| emigrants[from].iter().cloned())
+					.collect::<Vec<Creature>>()
+			};
+
+			// Never let immigrants take over more than half of an island
+			immigrants.sort_by(|a, b| b.cmp(a));
+			immigrants.truncate(island.creatures.len() / 2);
+
+			let natives = island.creatures.len() - immigrants.len();
+			island.creatures.truncate(natives);
+			island.creatures.extend(immigrants);
+			island.sort_by_fittest();
+		}
+	}
+}
+
+impl OptimisationMethod for IslandModel {
+	fn generation_single(&mut self) -> GenResult {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+
+		if self.data.print { println!(
+			"IM - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}",
+			self.data.gen,
+			self.data.generations[self.data.gen]
+			    .creatures[gen_size - 1]
+			    .fitness,
+			self.data.generations[self.data.gen].fitness_average(),
+			self.data.generations[self.data.gen].creatures[0].fitness
+		); }
+
+		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		let mut islands = self.offspring();
+		if self.interval > 0 && (self.data.gen + 1) % self.interval == 0 {
+			self.migrate(&mut islands);
+		}
+
+		let mut new_population = Population::empty(gen_size);
+		for island in &islands {
+			new_population.creatures.extend(island.creatures.iter().cloned());
+		}
+		new_population.sort_by_fittest();
+
+		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		self.data.groups.push(
+			islands.iter()
+				.map(|island| GroupStats::new(&island.creatures))
+				.collect()
+		);
+		self.islands = islands;
+
+		self.data.generations.push(new_population);
+		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
+		self.data.gen += 1;
+
+		Ok(())
+	}
+
+	fn creature_get (&mut self, gen: usize, idx: usize) -> &mut Creature {
+		&mut self.data.generations[gen].creatures[idx]
+	}
+
+	fn get_data_mut(&mut self) -> &mut OpMethodData {
+		&mut self.data
+	}
+
+	fn get_data(&self) -> &OpMethodData {
+		&self.data
+	}
+
+	fn reset(&mut self) {
+		self.islands = self.data.generations[self.data.gen]
+			.deal(self.island_count);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use population::Population;
+	use optimisationmethods::island_model::{IslandModel, Topology, ISLANDS};
+	use optimisationmethods::OptimisationMethod;
+
+	/// Run 10 generations with each topology and make sure the average
+	/// fitness has increased, with statistics kept for every island
+	#[test]
+	fn fitness_10gens() {
+		let mut rng = rand::thread_rng();
+
+		for &topology in &[Topology::Ring, Topology::FullyConnected] {
+			let population = Population::new(100, &mut rng);
+			let mut im = IslandModel::new(population, false, topology, ISLANDS);
+
+			for _ in 0 .. 10 {
+				let _ = im.generation_single();
+			}
+
+			let initial_fitness = im.get_data().creature_get_average(0);
+			let final_fitness = im.get_data().creature_get_average(10);
+			assert!(final_fitness > initial_fitness);
+
+			let groups = &im.get_data().groups;
+			assert_eq!(groups.len(), 10);
+			for stats in groups {
+				assert_eq!(stats.len(), 4);
+				assert_eq!(stats.iter().fold(0, |sum, s| sum + s.size), 100);
+			}
+		}
+	}
+
+	/// Make sure islands which never migrate can still evolve, rather than
+	/// dividing by a migration interval of 0
+	#[test]
+	fn migrate_never() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(20, &mut rng);
+		let mut im =
+			IslandModel::new(population, false, Topology::Ring, ISLANDS);
+		im.interval = 0;
+
+		for _ in 0 .. 5 {
+			assert!(im.generation_single().is_ok());
+		}
+		assert_eq!(im.get_data().groups.len(), 5);
+	}
+
+	/// Give each island creatures of a single fitness, then make sure
+	/// migrating on a ring only sends creatures to the next island, while
+	/// fully connected islands receive them from all the others
+	#[test]
+	fn migrate() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(10, &mut rng);
+		let islands = (0 .. 3)
+			.map(|idx| {
+				let mut island = population.clone();
+				for creature in &mut island.creatures {
+					creature.fitness = idx as f32;
+				}
+				island
+			})
+			.collect::<Vec<Population>>();
+
+		let mut im =
+			IslandModel::new(population, false, Topology::Ring, ISLANDS);
+		let mut ring = islands.clone();
+		im.migrate(&mut ring);
+		assert_eq!(ring[1].creatures[0].fitness, 1.0);
+		assert_eq!(ring[0].creatures[0].fitness, 2.0);
+		assert_eq!(
+			ring[0].creatures.iter().filter(|c| c.fitness == 2.0).count(),
+			2
+		);
+
+		im.topology = Topology::FullyConnected;
+		let mut full = islands.clone();
+		im.migrate(&mut full);
+		assert_eq!(full[0].creatures.len(), 10);
+		assert_eq!(
+			full[0].creatures.iter().filter(|c| c.fitness != 0.0).count(),
+			4
+		);
+	}
+}
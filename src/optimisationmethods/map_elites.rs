@@ -0,0 +1,370 @@
+use population::Population;
+use creature::Creature;
+use physics::{self, Simulation};
+use cache::FitnessCache;
+use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
+use optimisationmethods::genetic_algorithm::GeneticAlgorithm;
+use rand::{self, Rng, ThreadRng};
+use std::ops::Range;
+use time;
+use rayon::prelude::*;
+
+pub const MUTABILITY_RATE: f32 = 0.05;
+
+/// Chance of a child being bred from two elites rather than mutated from one
+pub const CROSSOVER_RATE: f32 = 0.5;
+
+/// Number of cells along each side of the grid, for descriptors which aren't
+/// already split into whole numbers
+pub const BINS: usize = 10;
+
+/// Measurements of how a creature is built or how it moved, used to decide
+/// which cell of the archive it belongs in
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Descriptor {
+	/// How many nodes the creature is made of
+	Nodes,
+
+	/// Fraction of the time its nodes spent touching the ground
+	GroundContact,
+
+	/// Highest its lowest node got off the ground after landing
+	JumpHeight,
+
+	/// How far it travelled, the same as its fitness
+	Distance
+}
+
+impl Descriptor {
+	/// Returns the creature's value for this descriptor, from its last
+	/// simulation
+	pub fn value(&self, creature: &Creature) -> f32 {
+		match *self {
+			Descriptor::Nodes => creature.nodes.len() as f32,
+			Descriptor::GroundContact => creature.behaviour.ground_contact,
+			Descriptor::JumpHeight => creature.behaviour.height,
+			Descriptor::Distance => creature.fitness
+		}
+	}
+
+	/// Returns the range of values spread over the cells. Values outside of
+	/// it are put into the first or last cell.
+	pub fn range(&self) -> Range<f32> {
+		match *self {
+			Descriptor::Nodes => 3.0 .. 7.0,
+			Descriptor::GroundContact => 0.0 .. 1.0,
+			Descriptor::JumpHeight => 0.0 .. 64.0,
+			Descriptor::Distance => -200.0 .. 1800.0
+		}
+	}
+
+	/// Returns the number of cells the range is split into
+	pub fn bins(&self) -> usize {
+		match *self {
+			Descriptor::Nodes => 4,
+			_ => BINS
+		}
+	}
+
+	/// Returns which cell along this descriptor's side of the grid a value
+	/// falls into
+	pub fn bin(&self, value: f32) -> usize {
+		let range = self.range();
+		let bins = self.bins() as f32;
+		let bin = (value - range.start) / (range.end - range.start) * bins;
+
+		physics::clamp(bin, 0.0 .. bins - 1.0) as usize
+	}
+
+	/// Returns the range of values which fall into a cell
+	pub fn bin_range(&self, bin: usize) -> Range<f32> {
+		let range = self.range();
+		let width = (range.end - range.start) / self.bins() as f32;
+		let start = range.start + width * bin as f32;
+
+		start .. start + width
+	}
+
+	/// Returns the descriptor's name, for labelling exported data
+	pub fn title(&self) -> &'static str {
+		match *self {
+			Descriptor::Nodes => "Nodes",
+			Descriptor::GroundContact => "Ground Contact",
+			Descriptor::JumpHeight => "Jump Height",
+			Descriptor::Distance => "Distance"
+		}
+	}
+}
+
+/// A grid of cells over two descriptors, each holding the fittest creature
+/// found so far with that pair of descriptor values
+pub struct Archive {
+	pub descriptors: (Descriptor, Descriptor),
+	pub cells: Vec<Option<Creature>>
+}
+
+impl Archive {
+	pub fn new(descriptors: (Descriptor, Descriptor)) -> Archive {
+		Archive {
+			descriptors: descriptors,
+			cells: vec![None; descriptors.0.bins() * descriptors.1.bins()]
+		}
+	}
+
+	/// Returns the index of the cell a creature belongs in
+	pub fn cell(&self, creature: &Creature) -> usize {
+		let x = self.descriptors.0.bin(self.descriptors.0.value(creature));
+		let y = self.descriptors.1.bin(self.descriptors.1.value(creature));
+
+		y * self.descriptors.0.bins() + x
+	}
+
+	/// Returns the column and row of a cell in the grid
+	pub fn position(&self, cell: usize) -> (usize, usize) {
+		(cell % self.descriptors.0.bins(), cell / self.descriptors.0.bins())
+	}
+
+	/// Puts a creature into its cell if the cell is empty or the creature is
+	/// fitter than the one already there, returning whether it was added
+	pub fn insert(&mut self, creature: &Creature) -> bool {
+		let cell = self.cell(creature);
+
+		let fitter = match self.cells[cell] {
+			Some(ref elite) => creature.fitness > elite.fitness,
+			None => true
+		};
+
+		if fitter {
+			self.cells[cell] = Some(creature.clone());
+		}
+
+		fitter
+	}
+
+	/// Returns the index of every cell which holds a creature
+	pub fn elites(&self) -> Vec<usize> {
+		(0 .. self.cells.len())
+			.filter(|&cell| self.cells[cell].is_some())
+			.collect()
+	}
+
+	/// Returns the fraction of the cells which hold a creature
+	pub fn coverage(&self) -> f32 {
+		self.elites().len() as f32 / self.cells.len() as f32
+	}
+}
+
+/// MAP-Elites. Rather than searching for a single champion, this keeps the
+/// fittest creature found for every combination of two descriptors, such as
+/// how many nodes a creature has and how much it touches the ground, and
+/// breeds new creatures from any of them.
+pub struct MapElites {
+	pub data: OpMethodData,
+	pub archive: Archive
+}
+
+impl MapElites {
+	pub fn new(
+		population: Population,
+		print: bool,
+		descriptors: (Descriptor, Descriptor)
+	) -> Box<MapElites> {
+		let mut me = MapElites {
+			data: OpMethodData::new(vec![population], "ME".to_string(), print),
+			archive: Archive::new(descriptors)
+		};
+		me.reset();
+
+		Box::new(me)
+	}
+
+	/// Breeds a generation's worth of children from creatures chosen at
+	/// random from the archive
+	fn offspring(&self, gen_size: usize) -> Vec<Creature> {
+		let elites = self.archive.elites();
+		let cells = &self.archive.cells;
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+		let mut offspring = Vec::with_capacity(gen_size);
+
+		(0 .. gen_size)
+		.into_par_iter()
+		.map(|_| {
+			let mut rng_new = rand::thread_rng();
+			child(cells, &elites, simulation, cache, &mut rng_new)
+		})
+		.collect_into(&mut offspring);
+
+		offspring
+	}
+}
+
+/// Breeds and simulates a single child from one or two elites
+fn child(
+	cells: &[Option<Creature>],
+	elites: &[usize],
+	simulation: &Simulation,
+	cache: &FitnessCache,
+	rng: &mut ThreadRng
+) -> Creature {
+	let parent_a = elite(cells, elites, rng);
+
+	let child = if rng.gen::<f32>() < CROSSOVER_RATE {
+		let parent_b = elite(cells, elites, rng);
+		GeneticAlgorithm::crossover(parent_a, parent_b, rng)
+	} else {
+		parent_a.clone()
+	};
+
+	let mut child = OpMethodData::mutate(&child, rng, MUTABILITY_RATE);
+	cache.simulate(&mut child, simulation);
+	child
+}
+
+/// Picks a creature from a random occupied cell of the archive
+fn elite<'a>(
+	cells: &'a [Option<Creature>],
+	elites: &[usize],
+	rng: &mut ThreadRng
+) -> &'a Creature {
+	let cell = elites[rng.gen_range(0, elites.len())];
+	cells[cell].as_ref().unwrap()
+}
+
+impl OptimisationMethod for MapElites {
+	fn generation_single(&mut self) -> GenResult {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+
+		if self.data.print { println!(
+			"ME - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}\tElites: {}",
+			self.data.gen,
+			self.data.generations[self.data.gen]
+			    .creatures[gen_size - 1]
+			    .fitness,
+			self.data.generations[self.data.gen].fitness_average(),
+			self.data.generations[self.data.gen].creatures[0].fitness,
+			self.archive.elites().len()
+		); }
+
+		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		let mut new_population = Population::empty(gen_size);
+		new_population.creatures = self.offspring(gen_size);
+
+		for creature in &new_population.creatures {
+			self.archive.insert(creature);
+		}
+
+		new_population.sort_by_fittest();
+
+		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		self.data.generations.push(new_population);
+		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
+		self.data.gen += 1;
+
+		Ok(())
+	}
+
+	fn creature_get (&mut self, gen: usize, idx: usize) -> &mut Creature {
+		&mut self.data.generations[gen].creatures[idx]
+	}
+
+	fn get_data_mut(&mut self) -> &mut OpMethodData {
+		&mut self.data
+	}
+
+	fn get_data(&self) -> &OpMethodData {
+		&self.data
+	}
+
+	fn archive(&self) -> Option<&Archive> {
+		Some(&self.archive)
+	}
+
+	fn archive_mut(&mut self) -> Option<&mut Archive> {
+		Some(&mut self.archive)
+	}
+
+	fn reset(&mut self) {
+		self.archive = Archive::new(self.archive.descriptors);
+		for creature in &self.data.generations[self.data.gen].creatures {
+			self.archive.insert(creature);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use creature::Creature;
+	use population::Population;
+	use optimisationmethods::map_elites::{Archive, Descriptor, MapElites};
+	use optimisationmethods::OptimisationMethod;
+
+	/// Make sure values are split evenly over the cells, and values outside
+	/// of a descriptor's range end up in its first or last cell
+	#[test]
+	fn descriptor_bin() {
+		assert_eq!(Descriptor::Nodes.bin(3.0), 0);
+		assert_eq!(Descriptor::Nodes.bin(6.0), 3);
+		assert_eq!(Descriptor::GroundContact.bin(0.55), 5);
+		assert_eq!(Descriptor::GroundContact.bin(1.0), 9);
+		assert_eq!(Descriptor::Distance.bin(-1000.0), 0);
+		assert_eq!(Descriptor::Distance.bin(5000.0), 9);
+		assert_eq!(Descriptor::Distance.bin_range(1), 0.0 .. 200.0);
+	}
+
+	/// Make sure a cell only ever swaps its creature for a fitter one
+	#[test]
+	fn archive_insert() {
+		let mut rng = rand::thread_rng();
+		let mut archive =
+			Archive::new((Descriptor::Nodes, Descriptor::GroundContact));
+
+		let mut creature = Creature::new(&mut rng);
+		creature.fitness = 10.0;
+		assert!(archive.insert(&creature));
+
+		creature.fitness = 5.0;
+		assert!(!archive.insert(&creature));
+
+		creature.fitness = 20.0;
+		assert!(archive.insert(&creature));
+
+		let cell = archive.cell(&creature);
+		assert_eq!(archive.elites(), vec![cell]);
+		assert_eq!(archive.cells[cell].as_ref().unwrap().fitness, 20.0);
+	}
+
+	/// Run 10 generations and make sure the archive never loses a cell, and
+	/// that every creature in it is in the right cell
+	#[test]
+	fn archive_10gens() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(100, &mut rng);
+		let mut me = MapElites::new(
+			population,
+			false,
+			(Descriptor::Nodes, Descriptor::GroundContact)
+		);
+
+		let _ = me.generation_single();
+		let coverage = me.archive.coverage();
+
+		for _ in 0 .. 9 {
+			let _ = me.generation_single();
+		}
+
+		assert_eq!(me.get_data().generations.len(), 11);
+		assert!(me.archive.coverage() >= coverage);
+		assert!(me.archive.elites().len() > 1);
+
+		for cell in me.archive.elites() {
+			let elite = me.archive.cells[cell].as_ref().unwrap();
+			assert_eq!(me.archive.cell(elite), cell);
+		}
+	}
+}
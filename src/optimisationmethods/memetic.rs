@@ -0,0 +1,290 @@
+use population::Population;
+use creature::Creature;
+use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
+use optimisationmethods::genetic_algorithm::GeneticAlgorithm;
+use rand::{self, ThreadRng};
+use std::cmp;
+use time;
+use rayon::prelude::*;
+
+pub const MUTABILITY_RATE: f32 = 0.05;
+
+/// Number of hill climbing steps each refined child takes
+pub const CLIMB_STEPS: usize = 2;
+
+/// What a child keeps from hill climbing
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Learning {
+	/// The improved creature replaces the child, so what it learned is
+	/// passed on to its own children
+	Lamarckian,
+
+	/// The child keeps its genome and its own fitness, and the improved
+	/// fitness is only used to select it, so learning only guides which
+	/// children are chosen as parents
+	Baldwinian
+}
+
+/// Memetic Algorithm. Children are bred with the genetic algorithm's
+/// tournament selection, crossover and mutation, then improved with a few
+/// steps of hill climbing before they compete to be selected.
+pub struct Memetic {
+	pub data: OpMethodData,
+	pub learning: Learning,
+
+	// Number of the fittest children refined each generation, or None to
+	// refine every child
+	pub refine: Option<usize>,
+
+	// Number of hill climbing steps each refined child takes
+	pub climb_steps: usize,
+
+	// Score each creature in the current generation is selected by, in the
+	// same order as its creatures. It's the fitness the creature reached by
+	// hill climbing, which is only different to its own with Baldwinian
+	// learning.
+	pub scores: Vec<f32>
+}
+
+impl Memetic {
+	pub fn new(
+		population: Population,
+		print: bool,
+		learning: Learning
+	) -> Box<Memetic> {
+		let title = match learning {
+			Learning::Lamarckian => "MA Lamarckian",
+			Learning::Baldwinian => "MA Baldwinian"
+		};
+
+		let mut ma = Memetic {
+			data: OpMethodData::new(vec![population], title.to_string(), print),
+			learning: learning,
+			refine: None,
+			climb_steps: CLIMB_STEPS,
+			scores: Vec::new()
+		};
+		ma.reset();
+
+		Box::new(ma)
+	}
+
+	/// Improves a child with a few steps of hill climbing, returning the
+	/// fitness it reached to select it by. Lamarckian learning replaces the
+	/// child with the improved creature, while Baldwinian learning leaves it
+	/// alone.
+	pub fn refine(&self, child: &mut Creature, rng: &mut ThreadRng) -> f32 {
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+		let mut refined = child.clone();
+
+		for _ in 0 .. self.climb_steps {
+			let mut neighbour =
+				OpMethodData::mutate(&refined, rng, MUTABILITY_RATE);
+			cache.simulate(&mut neighbour, simulation);
+
+			if neighbour.fitness > refined.fitness {
+				refined = neighbour;
+			}
+		}
+
+		let score = refined.fitness;
+		if self.learning == Learning::Lamarckian {
+			*child = refined;
+		}
+		score
+	}
+
+	/// Breeds and simulates a child for every creature in the generation,
+	/// choosing parents by their score
+	fn offspring(&self, gen_size: usize) -> Vec<Creature> {
+		let parents = &self.data.generations[self.data.gen].creatures;
+		let scores = &self.scores;
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+		let mut offspring = Vec::with_capacity(gen_size);
+
+		(0 .. gen_size)
+		.into_par_iter()
+		.map(|_| {
+			let mut rng = rand::thread_rng();
+
+			let creature_a =
+				GeneticAlgorithm::tournament_scores(parents, scores, &mut rng);
+			let creature_b =
+				GeneticAlgorithm::tournament_scores(parents, scores, &mut rng);
+
+			GeneticAlgorithm::child(
+				creature_a,
+				creature_b,
+				simulation,
+				cache,
+				&mut rng
+			)
+		})
+		.collect_into(&mut offspring);
+
+		offspring
+	}
+}
+
+impl OptimisationMethod for Memetic {
+	fn generation_single(&mut self) -> GenResult {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+		let mut new_population = Population::empty(gen_size);
+
+		if self.data.print { println!(
+			"{} - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}",
+			self.data.title,
+			self.data.gen,
+			self.data.generations[self.data.gen]
+			    .creatures[gen_size - 1]
+			    .fitness,
+			self.data.generations[self.data.gen].fitness_average(),
+			self.data.generations[self.data.gen].creatures[0].fitness
+		); }
+
+		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		let mut offspring = self.offspring(gen_size);
+		offspring.sort_by(|a, b| b.cmp(a));
+
+		// Only the fittest children are worth refining when there's a limit,
+		// and they're the first ones now the children are sorted. The rest
+		// are selected by their own fitness.
+		let refined = cmp::min(self.refine.unwrap_or(gen_size), gen_size);
+		let mut children = Vec::with_capacity(gen_size);
+		{
+			let memetic = &*self;
+			offspring
+			.into_par_iter()
+			.enumerate()
+			.map(|(idx, mut child)| {
+				let score = if idx < refined {
+					let mut rng_new = rand::thread_rng();
+					memetic.refine(&mut child, &mut rng_new)
+				} else {
+					child.fitness
+				};
+				(child, score)
+			})
+			.collect_into(&mut children);
+		}
+		children.sort_by(|a, b| b.0.cmp(&a.0));
+
+		let (creatures, scores): (Vec<Creature>, Vec<f32>) =
+			children.into_iter().unzip();
+		new_population.creatures = creatures;
+		self.scores = scores;
+
+		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		self.data.generations.push(new_population);
+		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
+		self.data.gen += 1;
+
+		Ok(())
+	}
+
+	fn creature_get (&mut self, gen: usize, idx: usize) -> &mut Creature {
+		&mut self.data.generations[gen].creatures[idx]
+	}
+
+	fn get_data_mut(&mut self) -> &mut OpMethodData {
+		&mut self.data
+	}
+
+	fn get_data(&self) -> &OpMethodData {
+		&self.data
+	}
+
+	// Fitness learned under a different simulation is out of date, so every
+	// creature starts again with its own
+	fn reset(&mut self) {
+		self.scores = self.data.generations[self.data.gen].creatures.iter()
+			.map(|creature| creature.fitness)
+			.collect();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use physics::{self, Simulation};
+	use population::Population;
+	use optimisationmethods::memetic::{Learning, Memetic};
+	use optimisationmethods::OptimisationMethod;
+
+	/// Run 10 generations with each kind of learning and make sure the
+	/// average fitness has increased
+	#[test]
+	fn fitness_10gens() {
+		let mut rng = rand::thread_rng();
+
+		for &learning in &[Learning::Lamarckian, Learning::Baldwinian] {
+			let population = Population::new(100, &mut rng);
+			let mut ma = Memetic::new(population, false, learning);
+			ma.refine = Some(20);
+
+			for _ in 0 .. 10 {
+				let _ = ma.generation_single();
+			}
+
+			let initial_fitness = ma.get_data().creature_get_average(0);
+			let final_fitness = ma.get_data().creature_get_average(10);
+
+			assert!(final_fitness > initial_fitness);
+		}
+	}
+
+	/// Refine the same creature with each kind of learning, making sure
+	/// neither loses fitness and Baldwinian learning leaves the creature alone
+	#[test]
+	fn refine() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(10, &mut rng);
+		let creature = population.creatures[0].clone();
+
+		let mut ma =
+			Memetic::new(population.clone(), false, Learning::Baldwinian);
+		ma.climb_steps = 5;
+		let mut child = creature.clone();
+		let score = ma.refine(&mut child, &mut rng);
+		assert!(score >= creature.fitness);
+		assert_eq!(child.fitness, creature.fitness);
+		assert_eq!(child.genome_hash(), creature.genome_hash());
+
+		let mut ma = Memetic::new(population, false, Learning::Lamarckian);
+		ma.climb_steps = 5;
+		let mut child = creature.clone();
+		let score = ma.refine(&mut child, &mut rng);
+		assert!(child.fitness >= creature.fitness);
+		assert_eq!(score, child.fitness);
+	}
+
+	/// Run a generation with Baldwinian learning, then simulate every child
+	/// again to make sure the fitness it was given is its own, and only its
+	/// score includes what it learned
+	#[test]
+	fn baldwinian_fitness() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(20, &mut rng);
+		let mut ma = Memetic::new(population, false, Learning::Baldwinian);
+		let _ = ma.generation_single();
+
+		let creatures = &ma.get_data().generations[1].creatures;
+		assert_eq!(ma.scores.len(), creatures.len());
+		for (creature, &score) in creatures.iter().zip(&ma.scores) {
+			let mut resimulated = creature.clone();
+			physics::full_simulation_creature(
+				&mut resimulated,
+				&Simulation::default()
+			);
+
+			assert_eq!(resimulated.fitness, creature.fitness);
+			assert!(score >= creature.fitness);
+		}
+	}
+}
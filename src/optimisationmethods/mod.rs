@@ -1,26 +1,78 @@
 use rand::{Rng, ThreadRng};
 use std::ops::Range;
-use creature::{self, Creature};
+use creature::{self, Creature, Objective, Waveform};
+use controller;
 use population::Population;
+use physics::Simulation;
+use cache::FitnessCache;
+use optimisationmethods::map_elites::Archive;
 use std::string::String;
 
 pub mod genetic_algorithm;
 pub mod hill_climbing;
 pub mod simulated_annealing;
+pub mod nsga2;
+pub mod novelty_search;
+pub mod map_elites;
+pub mod random_search;
+pub mod evolution_strategies;
+pub mod cma_es;
+pub mod differential_evolution;
+pub mod particle_swarm;
+pub mod tabu_search;
+pub mod memetic;
+pub mod island_model;
+pub mod speciation;
+pub mod parallel_tempering;
 
 const PROB_NODE_CHANGE: f32 = 8.0; // will be 1 / x
+const PROB_WAVEFORM_CHANGE: f32 = 32.0; // will be 1 / x
 
 /// GenResult
 /// Expects Ok(()) or Err((Title, Message))
 pub type GenResult = Result<(), (String, String)>;
 
+/// Fitness of a single sub-population in a generation, such as an island
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GroupStats {
+	pub size: usize,
+	pub fittest: f32,
+	pub average: f32
+}
+
+impl GroupStats {
+	pub fn new(creatures: &[Creature]) -> GroupStats {
+		let fittest = creatures.iter().max()
+			.map_or(0.0, |fittest| fittest.fitness);
+		let total = creatures.iter()
+			.fold(0.0, |total, creature| total + creature.fitness);
+
+		GroupStats {
+			size: creatures.len(),
+			fittest: fittest,
+			average: if creatures.is_empty() {
+				0.0
+			} else {
+				total / creatures.len() as f32
+			}
+		}
+	}
+}
+
 pub struct OpMethodData {
 	pub generations: Vec<Population>,
 	pub gen: usize,
 	pub gen_time: Vec<f32>,
 	pub spectate_creature: usize,
 	pub title: String,
-	pub print: bool
+	pub print: bool,
+	pub simulation: Simulation,
+	pub cache: FitnessCache,
+	pub cache_hit_rate: Vec<f32>,
+
+	// Statistics for each sub-population of every generation after the
+	// first, for methods which evolve several separately
+	pub groups: Vec<Vec<GroupStats>>
 }
 
 impl OpMethodData {
@@ -35,10 +87,22 @@ impl OpMethodData {
 			gen_time: Vec::new(),
 			spectate_creature: 0,
 			title: title,
-			print: print
+			print: print,
+			simulation: Simulation::default(),
+			cache: FitnessCache::new(),
+			cache_hit_rate: Vec::new(),
+			groups: Vec::new()
 		}
 	}
 
+	/// Changes the settings used to simulate each creature, recalculating the
+	/// current generation's fitness so it can be compared fairly
+	pub fn simulation_set(&mut self, simulation: Simulation) {
+		self.simulation = simulation;
+		self.cache.clear();
+		self.generations[self.gen].simulate(&self.simulation);
+	}
+
 	/// Returns the highest fitness value from the entire data structure
 	pub fn generations_get_fittest(&self) -> f32 {
 		let mut max = 0.0;
@@ -109,6 +173,18 @@ impl OpMethodData {
 		total / self.gen_time.len() as f32
 	}
 
+	/// Returns the average fraction of creatures whose fitness came from the
+	/// cache in each generation
+	pub fn average_cache_hit_rate(&self) -> f32 {
+		if self.cache_hit_rate.len() == 0 { return 0.0 }
+
+		let mut total: f32 = 0.0;
+		for hit_rate in &self.cache_hit_rate {
+			total += *hit_rate;
+		}
+		total / self.cache_hit_rate.len() as f32
+	}
+
 	/// Mutates a creature by adding/removing nodes and muscles, as well as
 	/// slightly modifying their evolution properties
 	pub fn mutate(creature: &Creature, rng: &mut ThreadRng, rate: f32)
@@ -163,6 +239,12 @@ impl OpMethodData {
 			rng
 		);
 
+		// Any muscles added to a creature with a neural controller need their
+		// own neuron too
+		if new_creature.has_controller() {
+			new_creature.controller_add(rng);
+		}
+
 		// For each node in the creature
 		for node in &mut new_creature.nodes {
 			// Modify the values of each property by the specified rate, but
@@ -190,6 +272,10 @@ impl OpMethodData {
 			);
 		}
 
+		// Move the nodes to their new starting positions before recalculating
+		// the muscle lengths below
+		new_creature.reset_position();
+
 		// Do the same process as above by for the muscles.
 		for muscle in &mut new_creature.muscles {
 			muscle.range_mut(new_creature.nodes.len(), rng);
@@ -202,13 +288,21 @@ impl OpMethodData {
 					rng
 				);
 
-			muscle.len =
-				new_creature
-				.nodes[muscle.nodes.0]
-				.distance(&new_creature.nodes[muscle.nodes.1]);
+			muscle.contraction = OpMethodData::mutate_clamp(
+				muscle.contraction,
+				rate,
+				creature::BOUNDS_MUSCLE_CONTRACTION,
+				rng
+			);
+
+			muscle.extension = OpMethodData::mutate_clamp(
+				muscle.extension,
+				rate,
+				creature::BOUNDS_MUSCLE_EXTENSION,
+				rng
+			);
 
-			muscle.len_min = muscle.len * creature::BOUNDS_MUSCLE_LENGTH.start;
-			muscle.len_max = muscle.len * creature::BOUNDS_MUSCLE_LENGTH.end;
+			muscle.update_length(&new_creature.nodes);
 
 			muscle.time_extended = OpMethodData::mutate_clamp_int(
 				muscle.time_extended,
@@ -223,6 +317,36 @@ impl OpMethodData {
 				creature::BOUNDS_MUSCLE_TIME_CONTRACTED,
 				rng
 			);
+
+			muscle.phase = OpMethodData::mutate_clamp_int(
+				muscle.phase,
+				rate * 8.0,
+				creature::BOUNDS_MUSCLE_PHASE,
+				rng
+			);
+
+			// Occasionally switch to a different contraction waveform
+			if rng.gen::<f32>() * PROB_WAVEFORM_CHANGE <= 1.0 {
+				muscle.waveform = Waveform::random(rng);
+			}
+
+			if let Some(ref mut neuron) = muscle.neuron {
+				for weight in neuron.weights.iter_mut() {
+					*weight = OpMethodData::mutate_clamp(
+						*weight,
+						rate * 4.0,
+						controller::BOUNDS_NEURON_WEIGHT,
+						rng
+					);
+				}
+
+				neuron.bias = OpMethodData::mutate_clamp(
+					neuron.bias,
+					rate * 4.0,
+					controller::BOUNDS_NEURON_WEIGHT,
+					rng
+				);
+			}
 		}
 
 		// Finally, return the new creature with the modified values.
@@ -247,13 +371,15 @@ impl OpMethodData {
 		range: Range<u32>,
 		rng: &mut ThreadRng
 	) -> u32 {
+		// Calculate it as a signed value first so values near zero do not
+		// wrap around to the upper bounds
 		::std::cmp::max(
-			range.start,
+			range.start as i32,
 			::std::cmp::min(
-				(value as i32 + rng.gen_range(-rate, rate) as i32) as u32,
-				range.end
+				value as i32 + rng.gen_range(-rate, rate) as i32,
+				range.end as i32
 			)
-		)
+		) as u32
 	}
 }
 
@@ -262,12 +388,72 @@ pub trait OptimisationMethod {
 	fn creature_get      (&mut self, gen: usize, idx: usize) -> &mut Creature;
 	fn get_data_mut      (&mut self) -> &mut OpMethodData;
 	fn get_data          (&self) -> &OpMethodData;
+
+	/// Changes the settings used to simulate each creature, then sets the
+	/// method up again around the recalculated fitness values
+	fn simulation_set (&mut self, simulation: Simulation) {
+		self.get_data_mut().simulation_set(simulation);
+		self.reset();
+	}
+
+	/// Sets up any state the method keeps alongside the current generation,
+	/// such as the best positions it has found, from that generation's
+	/// fitness values
+	fn reset (&mut self) {}
+
+	/// Returns the objectives being optimised, which is only the distance
+	/// travelled unless the method optimises several at once
+	fn objectives (&self) -> Vec<Objective> {
+		vec![Objective::Distance]
+	}
+
+	/// Returns each objective's value for every creature on the Pareto front
+	/// of a generation, if the method optimises several objectives at once
+	fn pareto_front (&self, _gen: usize) -> Option<&Vec<Vec<f32>>> {
+		None
+	}
+
+	/// Returns the grid of the fittest creatures found for each combination
+	/// of behaviours, if the method keeps one
+	fn archive (&self) -> Option<&Archive> {
+		None
+	}
+
+	fn archive_mut (&mut self) -> Option<&mut Archive> {
+		None
+	}
+
+	/// Returns how large the changes made to creatures were in a generation,
+	/// if the method adapts them as it goes
+	fn step_size (&self, _gen: usize) -> Option<f32> {
+		None
+	}
+
+	/// Returns the average length of the tabu lists in a generation, if the
+	/// method keeps them
+	fn tabu_size (&self, _gen: usize) -> Option<f32> {
+		None
+	}
+
+	/// Returns how many tabu moves were allowed through aspiration in a
+	/// generation, if the method keeps tabu lists
+	fn aspiration_hits (&self, _gen: usize) -> Option<usize> {
+		None
+	}
+
+	/// Returns the fraction of swaps accepted between each pair of
+	/// neighbouring temperatures over the whole run, if the method swaps
+	/// creatures between them
+	fn swap_rates (&self) -> Option<Vec<f32>> {
+		None
+	}
 }
 
 #[cfg(test)]
 mod tests {
 	use rand;
-	use optimisationmethods::OpMethodData;
+	use creature::{self, Creature};
+	use optimisationmethods::{OpMethodData, GroupStats};
 	use population::Population;
 
 	/// Create a struct with two populations, both with one creature in,
@@ -374,6 +560,48 @@ mod tests {
 		assert_eq!(om.average_gen_time(), 150.0);
 	}
 
+	/// Make sure a group's statistics match its creatures, and an empty
+	/// group has none
+	#[test]
+	fn group_stats() {
+		let population = om_setup_double().generations[1].clone();
+		let stats = GroupStats::new(&population.creatures);
+		assert_eq!(stats.size, 2);
+		assert_approx_eq!(stats.fittest, 200.0);
+		assert_approx_eq!(stats.average, 150.0);
+
+		let empty = GroupStats::new(&[]);
+		assert_eq!(empty.size, 0);
+		assert_approx_eq!(empty.average, 0.0);
+	}
+
+	/// Mutate a creature many times and make sure its muscles always have a
+	/// contracted length shorter than their extended length
+	#[test]
+	fn mutate_muscle_lengths() {
+		let mut rng = rand::thread_rng();
+		let mut creature = Creature::new(&mut rng);
+
+		for _ in 0 .. 100 {
+			creature = OpMethodData::mutate(&creature, &mut rng, 0.05);
+
+			for muscle in &creature.muscles {
+				assert!(
+					muscle.len_min < muscle.len && muscle.len < muscle.len_max
+				);
+				assert!(
+					muscle.contraction >=
+					creature::BOUNDS_MUSCLE_CONTRACTION.start &&
+					muscle.contraction <= creature::BOUNDS_MUSCLE_CONTRACTION.end
+				);
+				assert!(
+					muscle.extension >= creature::BOUNDS_MUSCLE_EXTENSION.start &&
+					muscle.extension <= creature::BOUNDS_MUSCLE_EXTENSION.end
+				);
+			}
+		}
+	}
+
 	/// Make sure the floating-point value is randomly mutated within the
 	/// specified bounds
 	#[test]
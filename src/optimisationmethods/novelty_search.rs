@@ -0,0 +1,240 @@
+use population::Population;
+use creature::Creature;
+use physics::Behaviour;
+use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
+use optimisationmethods::genetic_algorithm::GeneticAlgorithm;
+use rand;
+use std::cmp::{self, Ordering};
+use time;
+use rayon::prelude::*;
+
+/// Number of nearest behaviours used to measure how novel a creature is
+pub const NEIGHBOURS: usize = 15;
+
+/// Number of the most novel children added to the archive each generation
+pub const ARCHIVE_ADD: usize = 4;
+
+/// Novelty Search. Instead of rewarding creatures for how far they travel,
+/// creatures are rewarded for moving differently to the rest of the
+/// population and to an archive of behaviours seen in past generations, so
+/// the search keeps exploring rather than settling on one gait.
+pub struct NoveltySearch {
+	pub data: OpMethodData,
+	pub archive: Vec<Behaviour>,
+
+	// How much of each creature's score comes from its fitness rather than
+	// its novelty, from 0.0 (pure novelty) to 1.0 (pure fitness)
+	pub blend: f32
+}
+
+impl NoveltySearch {
+	pub fn new(population: Population, print: bool) -> Box<NoveltySearch> {
+		Box::new(NoveltySearch {
+			data: OpMethodData::new(vec![population], "NS".to_string(), print),
+			archive: Vec::new(),
+			blend: 0.0
+		})
+	}
+
+	/// Returns how novel each creature's behaviour is compared to the other
+	/// creatures and the archive
+	fn novelty(&self, creatures: &[Creature]) -> Vec<f32> {
+		let behaviours = creatures.iter()
+			.map(|creature| &creature.behaviour)
+			.chain(self.archive.iter())
+			.collect::<Vec<&Behaviour>>();
+
+		let mut novelty = Vec::with_capacity(creatures.len());
+		(0 .. creatures.len())
+		.into_par_iter()
+		.map(|idx| novelty_score(idx, &behaviours))
+		.collect_into(&mut novelty);
+
+		novelty
+	}
+
+	/// Returns each creature's score, its novelty blended with its fitness
+	fn scores(&self, creatures: &[Creature]) -> Vec<f32> {
+		self.novelty(creatures).iter().zip(creatures.iter())
+			.map(|(novelty, creature)| {
+				novelty * (1.0 - self.blend) + creature.fitness * self.blend
+			})
+			.collect()
+	}
+
+	/// Breeds a child for every creature in the current generation, choosing
+	/// parents by their score
+	fn offspring(&self, scores: &[f32]) -> Vec<Creature> {
+		let parents = &self.data.generations[self.data.gen].creatures;
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+		let mut offspring = Vec::with_capacity(parents.len());
+
+		(0 .. parents.len())
+		.into_par_iter()
+		.map(|_| {
+			let mut rng = rand::thread_rng();
+
+			let creature_a =
+				GeneticAlgorithm::tournament_scores(parents, scores, &mut rng);
+			let creature_b =
+				GeneticAlgorithm::tournament_scores(parents, scores, &mut rng);
+
+			GeneticAlgorithm::child(
+				creature_a,
+				creature_b,
+				simulation,
+				cache,
+				&mut rng
+			)
+		})
+		.collect_into(&mut offspring);
+
+		offspring
+	}
+}
+
+/// Returns the average distance from one behaviour to its nearest neighbours
+/// among the others
+pub fn novelty_score(idx: usize, behaviours: &[&Behaviour]) -> f32 {
+	let mut distances = behaviours.iter().enumerate()
+		.filter(|&(other, _)| other != idx)
+		.map(|(_, behaviour)| behaviours[idx].distance(behaviour))
+		.collect::<Vec<f32>>();
+
+	if distances.is_empty() { return 0.0; }
+
+	distances.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+	let neighbours = &distances[.. cmp::min(NEIGHBOURS, distances.len())];
+
+	neighbours.iter().fold(0.0, |sum, distance| sum + distance) /
+	neighbours.len() as f32
+}
+
+impl OptimisationMethod for NoveltySearch {
+	fn generation_single(&mut self) -> GenResult {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+
+		if self.data.print { println!(
+			"NS - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}\tArchive: {}",
+			self.data.gen,
+			self.data.generations[self.data.gen]
+			    .creatures[gen_size - 1]
+			    .fitness,
+			self.data.generations[self.data.gen].fitness_average(),
+			self.data.generations[self.data.gen].creatures[0].fitness,
+			self.archive.len()
+		); }
+
+		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		let scores =
+			self.scores(&self.data.generations[self.data.gen].creatures);
+		let mut new_population = Population::empty(gen_size);
+		new_population.creatures = self.offspring(&scores);
+
+		// Remember the behaviours of the most novel children, so future
+		// generations are pushed away from them too
+		let novelty = self.novelty(&new_population.creatures);
+		let mut order = (0 .. gen_size).collect::<Vec<usize>>();
+		order.sort_by(|&a, &b| {
+			novelty[b].partial_cmp(&novelty[a]).unwrap_or(Ordering::Equal)
+		});
+		for &idx in order.iter().take(ARCHIVE_ADD) {
+			self.archive.push(new_population.creatures[idx].behaviour.clone());
+		}
+
+		new_population.sort_by_fittest();
+
+		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		self.data.generations.push(new_population);
+		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
+		self.data.gen += 1;
+
+		Ok(())
+	}
+
+	fn creature_get (&mut self, gen: usize, idx: usize) -> &mut Creature {
+		&mut self.data.generations[gen].creatures[idx]
+	}
+
+	fn get_data_mut(&mut self) -> &mut OpMethodData {
+		&mut self.data
+	}
+
+	fn get_data(&self) -> &OpMethodData {
+		&self.data
+	}
+
+	// Behaviours recorded under a different simulation can't be compared
+	// with the new ones, so the archive starts again
+	fn reset(&mut self) {
+		self.archive.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use physics::{Behaviour, Simulation};
+	use population::Population;
+	use optimisationmethods::novelty_search::{self, NoveltySearch};
+	use optimisationmethods::OptimisationMethod;
+
+	/// Make sure a behaviour far away from the rest is the most novel, and
+	/// that identical behaviours have no novelty at all between them
+	#[test]
+	fn novelty_score() {
+		let behaviour = |x: f32| Behaviour {
+			trace: vec![(x, 0.0); 4],
+			ground_contact: 0.0,
+			height: 0.0
+		};
+
+		let same = vec![behaviour(1.0), behaviour(1.0), behaviour(1.0)];
+		let same = same.iter().collect::<Vec<&Behaviour>>();
+		assert_eq!(novelty_search::novelty_score(0, &same), 0.0);
+
+		let spread = vec![behaviour(0.0), behaviour(1.0), behaviour(50.0)];
+		let spread = spread.iter().collect::<Vec<&Behaviour>>();
+		assert!(
+			novelty_search::novelty_score(2, &spread) >
+			novelty_search::novelty_score(1, &spread)
+		);
+	}
+
+	/// Run 10 generations and make sure the archive grows every generation
+	/// and that simulated creatures have recorded their behaviour
+	#[test]
+	fn archive_10gens() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(100, &mut rng);
+		let mut ns = NoveltySearch::new(population, false);
+
+		for _ in 0 .. 10 {
+			let _ = ns.generation_single();
+		}
+
+		assert_eq!(ns.archive.len(), novelty_search::ARCHIVE_ADD * 10);
+		assert_eq!(ns.get_data().generations.len(), 11);
+		for creature in &ns.get_data().generations[10].creatures {
+			assert!(!creature.behaviour.trace.is_empty());
+		}
+	}
+	/// Change the simulation part way through, making sure behaviours from
+	/// the old simulation are forgotten
+	#[test]
+	fn simulation_set() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(10, &mut rng);
+		let mut ns = NoveltySearch::new(population, false);
+		let _ = ns.generation_single();
+		assert!(!ns.archive.is_empty());
+
+		ns.simulation_set(Simulation::water());
+		assert!(ns.archive.is_empty());
+	}
+}
@@ -0,0 +1,407 @@
+use population::Population;
+use creature::{Creature, Objective};
+use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
+use optimisationmethods::genetic_algorithm::GeneticAlgorithm;
+use rand::{self, Rng, ThreadRng};
+use std::cmp::Ordering;
+use std::f32;
+use time;
+use rayon::prelude::*;
+
+pub const MUTABILITY_RATE: f32 = 0.05;
+
+/// Non-dominated Sorting Genetic Algorithm II. Rather than a single fitness,
+/// creatures are compared on several objectives at once, and the population
+/// spreads out along the Pareto front of creatures where no objective can be
+/// improved without making another one worse.
+pub struct Nsga2 {
+	pub data: OpMethodData,
+	pub objectives: Vec<Objective>,
+
+	// Objective values of each creature on the Pareto front, per generation
+	pub fronts: Vec<Vec<Vec<f32>>>
+}
+
+impl Nsga2 {
+	pub fn new(
+		population: Population,
+		print: bool,
+		objectives: Vec<Objective>
+	) -> Box<Nsga2> {
+		let mut nsga2 = Nsga2 {
+			data: OpMethodData::new(
+				vec![population],
+				"NSGA".to_string(),
+				print
+			),
+			objectives: objectives,
+			fronts: Vec::new()
+		};
+
+		let front = nsga2.front_record(0);
+		nsga2.fronts.push(front);
+
+		Box::new(nsga2)
+	}
+
+	/// Returns the objective values of every creature on the Pareto front of
+	/// the given generation
+	fn front_record(&self, gen: usize) -> Vec<Vec<f32>> {
+		let points = self.points(&self.data.generations[gen].creatures);
+		match non_dominated_sort(&points).into_iter().next() {
+			Some(front) => {
+				front.iter().map(|&idx| points[idx].clone()).collect()
+			},
+			None => Vec::new()
+		}
+	}
+
+	/// Ranks every creature in a generation by the front it is on and how
+	/// crowded it is there, so they can be selected
+	fn rank(&self, gen: usize) -> (Vec<usize>, Vec<f32>) {
+		let creatures = &self.data.generations[gen].creatures;
+		let points = self.points(creatures);
+		let mut rank = vec![0; creatures.len()];
+		let mut crowding = vec![0.0; creatures.len()];
+
+		let fronts = non_dominated_sort(&points);
+		for (front_idx, front) in fronts.iter().enumerate() {
+			let distance = crowding_distance(&points, front);
+			for (idx, &creature) in front.iter().enumerate() {
+				rank[creature] = front_idx;
+				crowding[creature] = distance[idx];
+			}
+		}
+
+		(rank, crowding)
+	}
+
+	/// Breeds a child for every creature in the current generation, choosing
+	/// parents by their rank
+	fn offspring(&self, rank: &[usize], crowding: &[f32]) -> Vec<Creature> {
+		let parents = &self.data.generations[self.data.gen].creatures;
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+		let mut offspring = Vec::with_capacity(parents.len());
+
+		(0 .. parents.len())
+		.into_par_iter()
+		.map(|_| {
+			let mut rng_new = rand::thread_rng();
+
+			let creature_a = selection(parents, rank, crowding, &mut rng_new);
+			let creature_b = selection(parents, rank, crowding, &mut rng_new);
+
+			let child = GeneticAlgorithm::crossover(
+				creature_a,
+				creature_b,
+				&mut rng_new
+			);
+
+			let mut child = OpMethodData::mutate(
+				&child,
+				&mut rng_new,
+				MUTABILITY_RATE
+			);
+
+			cache.simulate(&mut child, simulation);
+			child
+		})
+		.collect_into(&mut offspring);
+
+		offspring
+	}
+
+	/// Fills a new population one front at a time, splitting the last front
+	/// that doesn't fit by keeping its least crowded creatures
+	fn survivors(&self, creatures: Vec<Creature>, size: usize) -> Population {
+		let points = self.points(&creatures);
+		let mut chosen: Vec<usize> = Vec::with_capacity(size);
+
+		for front in non_dominated_sort(&points) {
+			if chosen.len() + front.len() <= size {
+				chosen.extend(front);
+			} else {
+				let distance = crowding_distance(&points, &front);
+				let mut order: Vec<usize> = (0 .. front.len()).collect();
+				order.sort_by(|&a, &b| {
+					distance[b].partial_cmp(&distance[a])
+						.unwrap_or(Ordering::Equal)
+				});
+
+				let remaining = size - chosen.len();
+				for &idx in &order[.. remaining] {
+					chosen.push(front[idx]);
+				}
+			}
+
+			if chosen.len() >= size { break; }
+		}
+
+		let mut population = Population::empty(size);
+		population.creatures =
+			chosen.iter().map(|&idx| creatures[idx].clone()).collect();
+		population.sort_by_fittest();
+		population
+	}
+
+	/// Returns the objective values of every creature
+	fn points(&self, creatures: &[Creature]) -> Vec<Vec<f32>> {
+		creatures.iter()
+			.map(|creature| creature.objectives(&self.objectives))
+			.collect()
+	}
+}
+
+/// Returns whether the first set of objective values dominates the second,
+/// being at least as good in every objective and better in at least one
+pub fn dominates(a: &[f32], b: &[f32]) -> bool {
+	let mut better = false;
+	for idx in 0 .. a.len() {
+		if a[idx] < b[idx] { return false; }
+		if a[idx] > b[idx] { better = true; }
+	}
+	better
+}
+
+/// Sorts points into fronts, returning the indices of the points in each.
+/// The first front holds every point that nothing dominates, the second
+/// holds the points only dominated by the first, and so on.
+pub fn non_dominated_sort(points: &[Vec<f32>]) -> Vec<Vec<usize>> {
+	let len = points.len();
+	let mut dominated_count = vec![0; len];
+	let mut dominating: Vec<Vec<usize>> = vec![Vec::new(); len];
+
+	for a in 0 .. len {
+		for b in a + 1 .. len {
+			if dominates(&points[a], &points[b]) {
+				dominating[a].push(b);
+				dominated_count[b] += 1;
+			} else if dominates(&points[b], &points[a]) {
+				dominating[b].push(a);
+				dominated_count[a] += 1;
+			}
+		}
+	}
+
+	let mut fronts = Vec::new();
+	let mut front: Vec<usize> =
+		(0 .. len).filter(|&idx| dominated_count[idx] == 0).collect();
+
+	while !front.is_empty() {
+		let mut next = Vec::new();
+		for &a in &front {
+			for &b in &dominating[a] {
+				dominated_count[b] -= 1;
+				if dominated_count[b] == 0 {
+					next.push(b);
+				}
+			}
+		}
+		fronts.push(front);
+		front = next;
+	}
+
+	fronts
+}
+
+/// Returns the crowding distance of each point in a front, in the same order
+/// as the front. Points at either end of any objective are always kept, and
+/// the others are measured by how far apart their neighbours are.
+pub fn crowding_distance(points: &[Vec<f32>], front: &[usize]) -> Vec<f32> {
+	let mut distance = vec![0.0; front.len()];
+	if front.is_empty() { return distance; }
+
+	for objective in 0 .. points[front[0]].len() {
+		let mut order: Vec<usize> = (0 .. front.len()).collect();
+		order.sort_by(|&a, &b| {
+			points[front[a]][objective]
+				.partial_cmp(&points[front[b]][objective])
+				.unwrap_or(Ordering::Equal)
+		});
+
+		let min = points[front[order[0]]][objective];
+		let max = points[front[order[order.len() - 1]]][objective];
+
+		distance[order[0]] = f32::INFINITY;
+		distance[order[order.len() - 1]] = f32::INFINITY;
+
+		if max <= min { continue; }
+
+		for idx in 1 .. order.len().saturating_sub(1) {
+			distance[order[idx]] +=
+				(points[front[order[idx + 1]]][objective] -
+				 points[front[order[idx - 1]]][objective]) / (max - min);
+		}
+	}
+
+	distance
+}
+
+/// Binary tournament using the crowded comparison, preferring creatures on a
+/// better front, then creatures in a less crowded part of their front
+fn selection<'a>(
+	creatures: &'a [Creature],
+	rank: &[usize],
+	crowding: &[f32],
+	rng: &mut ThreadRng
+) -> &'a Creature {
+	let a = rng.gen_range(0, creatures.len());
+	let b = rng.gen_range(0, creatures.len());
+
+	if rank[a] < rank[b] ||
+	   (rank[a] == rank[b] && crowding[a] > crowding[b]) {
+		&creatures[a]
+	} else {
+		&creatures[b]
+	}
+}
+
+impl OptimisationMethod for Nsga2 {
+	fn generation_single(&mut self) -> GenResult {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+		if self.data.print { println!(
+			"NSGA - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}\tFront: {}",
+			self.data.gen,
+			self.data.generations[self.data.gen]
+			    .creatures[gen_size - 1]
+			    .fitness,
+			self.data.generations[self.data.gen].fitness_average(),
+			self.data.generations[self.data.gen].creatures[0].fitness,
+			self.fronts[self.data.gen].len()
+		); }
+
+		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		let (rank, crowding) = self.rank(self.data.gen);
+
+		// Parents and children compete together for the next generation
+		let mut combined = self.offspring(&rank, &crowding);
+		combined.extend(
+			self.data.generations[self.data.gen].creatures.iter().cloned()
+		);
+		let new_population = self.survivors(combined, gen_size);
+
+		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		self.data.generations.push(new_population);
+		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
+		self.data.gen += 1;
+
+		let front = self.front_record(self.data.gen);
+		self.fronts.push(front);
+
+		Ok(())
+	}
+
+	fn creature_get (&mut self, gen: usize, idx: usize) -> &mut Creature {
+		&mut self.data.generations[gen].creatures[idx]
+	}
+
+	fn get_data_mut(&mut self) -> &mut OpMethodData {
+		&mut self.data
+	}
+
+	fn get_data(&self) -> &OpMethodData {
+		&self.data
+	}
+
+	fn objectives(&self) -> Vec<Objective> {
+		self.objectives.clone()
+	}
+
+	fn pareto_front(&self, gen: usize) -> Option<&Vec<Vec<f32>>> {
+		self.fronts.get(gen)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use std::f32;
+	use population::Population;
+	use creature::Objective;
+	use optimisationmethods::nsga2::{self, Nsga2};
+	use optimisationmethods::OptimisationMethod;
+
+	/// Make sure a point only dominates another when it is no worse in any
+	/// objective and better in at least one
+	#[test]
+	fn dominates() {
+		assert!(nsga2::dominates(&[2.0, 2.0], &[1.0, 2.0]));
+		assert!(!nsga2::dominates(&[2.0, 2.0], &[2.0, 2.0]));
+		assert!(!nsga2::dominates(&[2.0, 1.0], &[1.0, 2.0]));
+	}
+
+	/// Sort a handful of points and make sure each ends up on the right front
+	#[test]
+	fn non_dominated_sort() {
+		let points = vec![
+			vec![1.0, 1.0], // Dominated by everything else
+			vec![3.0, 1.0],
+			vec![1.0, 3.0],
+			vec![2.0, 2.0],
+			vec![1.5, 1.5]  // Only dominated by (2, 2)
+		];
+
+		let mut fronts = nsga2::non_dominated_sort(&points);
+		for front in &mut fronts {
+			front.sort();
+		}
+
+		assert_eq!(fronts, vec![vec![1, 2, 3], vec![4], vec![0]]);
+	}
+
+	/// Make sure the points at either end of a front are always kept, and
+	/// that a point with close neighbours is more crowded than one without
+	#[test]
+	fn crowding_distance() {
+		let points = vec![
+			vec![0.0, 4.0],
+			vec![1.0, 3.0],
+			vec![1.5, 2.5],
+			vec![4.0, 0.0]
+		];
+
+		let distance = nsga2::crowding_distance(&points, &[0, 1, 2, 3]);
+
+		assert_eq!(distance[0], f32::INFINITY);
+		assert_eq!(distance[3], f32::INFINITY);
+		assert!(distance[1] < distance[2]);
+	}
+
+	/// Run 10 generations, making sure the creature which travels furthest is
+	/// never lost, as it is always at one end of the Pareto front
+	#[test]
+	fn front_10gens() {
+		let mut rng = rand::thread_rng();
+
+		for _ in 0 .. 5 {
+			let population = Population::new(100, &mut rng);
+			let mut nsga2 = Nsga2::new(
+				population,
+				false,
+				vec![Objective::Distance, Objective::Energy]
+			);
+
+			for _ in 0 .. 10 {
+				let _ = nsga2.generation_single();
+			}
+
+			for gen in 0 .. 10 {
+				assert!(
+					nsga2.get_data().creature_get_fittest(gen + 1).fitness >=
+					nsga2.get_data().creature_get_fittest(gen).fitness
+				);
+			}
+
+			let front = nsga2.pareto_front(10).unwrap();
+			assert!(!front.is_empty());
+			for point in front {
+				assert_eq!(point.len(), 2);
+			}
+		}
+	}
+}
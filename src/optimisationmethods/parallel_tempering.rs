@@ -0,0 +1,342 @@
+use population::Population;
+use creature::Creature;
+use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
+use optimisationmethods::GroupStats;
+use rand::{self, Rng, ThreadRng};
+use std::cmp;
+use std::mem;
+use time;
+use rayon::prelude::*;
+
+pub const MUTABILITY_RATE: f32 = 0.05;
+
+/// Number of temperatures the population is split between
+pub const REPLICAS: usize = 4;
+
+/// Coldest and hottest temperatures, in units of fitness
+pub const TEMP_LOW: f32 = 1.0;
+pub const TEMP_HIGH: f32 = 100.0;
+
+/// Returns the given number of temperatures spaced evenly on a log scale
+/// from the coldest to the hottest
+pub fn temperatures(count: usize) -> Vec<f32> {
+	if count < 2 { return vec![TEMP_LOW] }
+
+	let ratio = (TEMP_HIGH / TEMP_LOW).powf(1.0 / (count - 1) as f32);
+	(0 .. count).map(|idx| TEMP_LOW * ratio.powi(idx as i32)).collect()
+}
+
+/// Returns whether every temperature is positive and they run from the
+/// coldest to the hottest, as otherwise acceptance chances can't be worked out
+pub fn temperatures_valid(temperatures: &[f32]) -> bool {
+	temperatures.iter().all(|&temp| temp > 0.0 && temp.is_finite()) &&
+	temperatures.windows(2).all(|pair| pair[0] <= pair[1])
+}
+
+/// Parallel Tempering. The population is split into replicas which each
+/// anneal at their own fixed temperature, where hot replicas readily accept
+/// less fit creatures and explore while cold ones only climb. Neighbouring
+/// replicas swap creatures with the Metropolis criterion every generation,
+/// so good creatures found by exploring sink down to be refined.
+pub struct ParallelTempering {
+	pub data: OpMethodData,
+
+	// Temperature of each replica, from coldest to hottest
+	pub temperatures: Vec<f32>,
+	pub replicas: Vec<Population>,
+
+	// Swaps attempted and accepted in total between each pair of
+	// neighbouring temperatures
+	pub swaps_attempted: Vec<usize>,
+	pub swaps_accepted: Vec<usize>,
+
+	// Fraction of swaps accepted between each pair of neighbouring
+	// temperatures, for every generation after the first
+	pub swap_rates: Vec<Vec<f32>>
+}
+
+impl ParallelTempering {
+	pub fn new(
+		population: Population,
+		print: bool,
+		temperatures: Vec<f32>
+	) -> Box<ParallelTempering> {
+		let mut pt = ParallelTempering {
+			data: OpMethodData::new(vec![population], "PT".to_string(), print),
+			temperatures: temperatures,
+			replicas: Vec::new(),
+			swaps_attempted: Vec::new(),
+			swaps_accepted: Vec::new(),
+			swap_rates: Vec::new()
+		};
+		pt.reset();
+
+		Box::new(pt)
+	}
+
+	/// Returns the fraction of all swaps accepted between the replicas at
+	/// the given temperature and the next hottest
+	pub fn swap_rate(&self, pair: usize) -> f32 {
+		if self.swaps_attempted[pair] == 0 { return 0.0 }
+		self.swaps_accepted[pair] as f32 / self.swaps_attempted[pair] as f32
+	}
+
+	/// Mutates every creature in every replica, accepting less fit creatures
+	/// with a chance depending on how much less fit they are and how hot
+	/// their replica is
+	fn offspring(&self) -> Vec<Population> {
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+		let mut replicas = Vec::with_capacity(self.replicas.len());
+
+		self.replicas
+		.par_iter()
+		.zip(self.temperatures.par_iter())
+		.map(|(replica, &temp)| {
+			let mut new_replica = Population::empty(replica.creatures.len());
+
+			replica.creatures
+			.par_iter()
+			.map(|creature| {
+				let mut rng_new = rand::thread_rng();
+				let mut new_creature = OpMethodData::mutate(
+					creature,
+					&mut rng_new,
+					MUTABILITY_RATE
+				);
+
+				cache.simulate(&mut new_creature, simulation);
+
+				let change = (new_creature.fitness - creature.fitness) / temp;
+				if change >= 0.0 || rng_new.gen::<f32>() < change.exp() {
+					new_creature
+				} else {
+					creature.clone()
+				}
+			})
+			.collect_into(&mut new_replica.creatures);
+
+			new_replica
+		})
+		.collect_into(&mut replicas);
+
+		replicas
+	}
+
+	/// Proposes swapping each creature with the one in the same place in
+	/// the next hottest replica, returning how many swaps were attempted and
+	/// accepted between each pair of neighbouring replicas
+	pub fn swap(
+		&self,
+		replicas: &mut Vec<Population>,
+		rng: &mut ThreadRng
+	) -> Vec<(usize, usize)> {
+		let pairs = replicas.len().saturating_sub(1);
+
+		(0 .. pairs).map(|pair| {
+			let (cold, hot) = replicas.split_at_mut(pair + 1);
+			let cold = &mut cold[pair].creatures;
+			let hot = &mut hot[0].creatures;
+			let beta = 1.0 / self.temperatures[pair] -
+				1.0 / self.temperatures[pair + 1];
+
+			let attempted = cmp::min(cold.len(), hot.len());
+			let mut accepted = 0;
+			for idx in 0 .. attempted {
+				// A fitter creature moving to the colder replica is always
+				// accepted
+				let change = (hot[idx].fitness - cold[idx].fitness) * beta;
+				if change >= 0.0 || rng.gen::<f32>() < change.exp() {
+					mem::swap(&mut cold[idx], &mut hot[idx]);
+					accepted += 1;
+				}
+			}
+
+			(attempted, accepted)
+		})
+		.collect()
+	}
+}
+
+impl OptimisationMethod for ParallelTempering {
+	fn generation_single(&mut self) -> GenResult {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+
+		if self.data.print { println!(
+			"PT - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}\tSwap Rates: {:?}",
+			self.data.gen,
+			self.data.generations[self.data.gen]
+			    .creatures[gen_size - 1]
+			    .fitness,
+			self.data.generations[self.data.gen].fitness_average(),
+			self.data.generations[self.data.gen].creatures[0].fitness,
+			self.swap_rates.last().unwrap_or(&Vec::new())
+		); }
+
+		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		let mut rng = rand::thread_rng();
+		let mut replicas = self.offspring();
+		let swaps = self.swap(&mut replicas, &mut rng);
+
+		let mut new_population = Population::empty(gen_size);
+		for replica in &replicas {
+			new_population.creatures.extend(replica.creatures.iter().cloned());
+		}
+		new_population.sort_by_fittest();
+
+		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		for (pair, &(attempted, accepted)) in swaps.iter().enumerate() {
+			self.swaps_attempted[pair] += attempted;
+			self.swaps_accepted[pair] += accepted;
+		}
+		self.swap_rates.push(
+			swaps.iter()
+				.map(|&(attempted, accepted)| {
+					if attempted == 0 {
+						0.0
+					} else {
+						accepted as f32 / attempted as f32
+					}
+				})
+				.collect()
+		);
+		self.data.groups.push(
+			replicas.iter()
+				.map(|replica| GroupStats::new(&replica.creatures))
+				.collect()
+		);
+		self.replicas = replicas;
+
+		self.data.generations.push(new_population);
+		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
+		self.data.gen += 1;
+
+		Ok(())
+	}
+
+	fn creature_get (&mut self, gen: usize, idx: usize) -> &mut Creature {
+		&mut self.data.generations[gen].creatures[idx]
+	}
+
+	fn get_data_mut(&mut self) -> &mut OpMethodData {
+		&mut self.data
+	}
+
+	fn get_data(&self) -> &OpMethodData {
+		&self.data
+	}
+
+	fn swap_rates(&self) -> Option<Vec<f32>> {
+		Some(
+			(0 .. self.swaps_attempted.len())
+				.map(|pair| self.swap_rate(pair))
+				.collect()
+		)
+	}
+
+	fn reset(&mut self) {
+		self.replicas = self.data.generations[self.data.gen]
+			.deal(self.temperatures.len());
+		let count = self.replicas.len();
+		self.temperatures.truncate(count);
+
+		self.swaps_attempted = vec![0; count - 1];
+		self.swaps_accepted = vec![0; count - 1];
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use population::Population;
+	use optimisationmethods::parallel_tempering::{self, ParallelTempering};
+	use optimisationmethods::OptimisationMethod;
+
+	/// Make sure temperatures run from the coldest to the hottest
+	#[test]
+	fn temperatures() {
+		let temperatures = parallel_tempering::temperatures(3);
+		assert_approx_eq!(temperatures[0], parallel_tempering::TEMP_LOW);
+		assert_approx_eq!(temperatures[1], 10.0, 0.001);
+		assert_approx_eq!(
+			temperatures[2],
+			parallel_tempering::TEMP_HIGH,
+			0.001
+		);
+	}
+
+	/// Make sure temperatures which are out of order, or which aren't
+	/// positive, are rejected
+	#[test]
+	fn temperatures_valid() {
+		assert!(parallel_tempering::temperatures_valid(&[1.0, 10.0, 100.0]));
+		assert!(!parallel_tempering::temperatures_valid(&[10.0, 1.0]));
+		assert!(!parallel_tempering::temperatures_valid(&[0.0, 1.0]));
+		assert!(!parallel_tempering::temperatures_valid(&[-1.0, 1.0]));
+	}
+
+	/// Run 10 generations and make sure a fitter creature was found than any
+	/// of the starting ones, with statistics kept for every replica and swap
+	/// rates for every pair of them
+	#[test]
+	fn fitness_10gens() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(100, &mut rng);
+		let mut pt = ParallelTempering::new(
+			population,
+			false,
+			parallel_tempering::temperatures(parallel_tempering::REPLICAS)
+		);
+
+		for _ in 0 .. 10 {
+			let _ = pt.generation_single();
+		}
+
+		let initial_fitness = pt.get_data().creature_get_fittest(0).fitness;
+		assert!(pt.get_data().generations_get_fittest() > initial_fitness);
+
+		assert_eq!(pt.get_data().groups.len(), 10);
+		assert_eq!(pt.swap_rates.len(), 10);
+		for rates in &pt.swap_rates {
+			assert_eq!(rates.len(), 3);
+		}
+		for pair in 0 .. 3 {
+			assert_eq!(pt.swaps_attempted[pair], 250);
+			assert!(pt.swap_rate(pair) >= 0.0 && pt.swap_rate(pair) <= 1.0);
+		}
+		assert_eq!(pt.swap_rates().unwrap().len(), 3);
+	}
+
+	/// Make sure there is a replica for every temperature given, and that
+	/// fitter creatures in a hotter replica are always swapped into the
+	/// colder one, but much less fit ones never are
+	#[test]
+	fn swap() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(10, &mut rng);
+		let pt = ParallelTempering::new(
+			population.clone(),
+			false,
+			parallel_tempering::temperatures(2)
+		);
+		assert_eq!(pt.replicas.len(), 2);
+		assert_eq!(pt.swaps_attempted.len(), 1);
+
+		let mut cold = population.clone();
+		let mut hot = population;
+		for creature in &mut cold.creatures { creature.fitness = 0.0; }
+		for creature in &mut hot.creatures { creature.fitness = 1000.0; }
+
+		let mut replicas = vec![cold.clone(), hot.clone()];
+		assert_eq!(pt.swap(&mut replicas, &mut rng), vec![(10, 10)]);
+		assert!(replicas[0].creatures.iter().all(|c| c.fitness == 1000.0));
+
+		let mut replicas = vec![hot, cold];
+		assert_eq!(pt.swap(&mut replicas, &mut rng), vec![(10, 0)]);
+		assert!(replicas[0].creatures.iter().all(|c| c.fitness == 1000.0));
+	}
+}
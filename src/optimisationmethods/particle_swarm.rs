@@ -0,0 +1,300 @@
+use population::Population;
+use creature::Creature;
+use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
+use rand::{self, Rng, ThreadRng};
+use std::cmp;
+use std::mem;
+use time;
+use rayon::prelude::*;
+
+/// How much of its velocity a particle keeps each generation (w)
+pub const INERTIA: f32 = 0.7;
+
+/// How strongly a particle is pulled towards its own best position (c1)
+pub const COGNITIVE: f32 = 1.5;
+
+/// How strongly a particle is pulled towards its neighbours' best (c2)
+pub const SOCIAL: f32 = 1.5;
+
+/// Fastest a gene can change in a single generation, as a fraction of its
+/// bounds
+pub const VELOCITY_MAX: f32 = 0.2;
+
+/// Chance each generation of a particle taking on the body plan of a
+/// neighbour's best when it is different to its own
+pub const ADOPT_RATE: f32 = 0.1;
+
+/// A single creature moving through the space of genes for its body plan
+#[derive(Clone)]
+pub struct Particle {
+	pub creature: Creature,
+	pub velocity: Vec<f32>,
+
+	// Fittest position the particle has been in
+	pub best: Creature
+}
+
+impl Particle {
+	pub fn new(creature: &Creature, rng: &mut ThreadRng) -> Particle {
+		Particle {
+			velocity: random_velocity(creature.genes().len(), rng),
+			creature: creature.clone(),
+			best: creature.clone()
+		}
+	}
+}
+
+/// Returns a random velocity for a particle with the given number of genes
+fn random_velocity(genes: usize, rng: &mut ThreadRng) -> Vec<f32> {
+	(0 .. genes).map(|_| rng.gen_range(-VELOCITY_MAX, VELOCITY_MAX)).collect()
+}
+
+/// Particle Swarm Optimisation. Each creature is a particle flying through
+/// the space of its genes, pulled towards the fittest position it has found
+/// and the fittest position found by its neighbours.
+pub struct ParticleSwarm {
+	pub data: OpMethodData,
+	pub particles: Vec<Particle>,
+	pub inertia: f32,
+	pub cognitive: f32,
+	pub social: f32,
+
+	// Number of particles either side of each particle in a ring which it
+	// follows the best of, or None to follow the best of the whole swarm
+	pub neighbourhood: Option<usize>
+}
+
+impl ParticleSwarm {
+	pub fn new(population: Population, print: bool) -> Box<ParticleSwarm> {
+		let mut pso = ParticleSwarm {
+			data: OpMethodData::new(vec![population], "PSO".to_string(), print),
+			particles: Vec::new(),
+			inertia: INERTIA,
+			cognitive: COGNITIVE,
+			social: SOCIAL,
+			neighbourhood: None
+		};
+		pso.reset();
+
+		Box::new(pso)
+	}
+
+	/// Moves a particle towards its own best and its leader's best, first
+	/// taking the leader's body plan now and then if it differs, as genes
+	/// only mean the same thing between creatures with the same body plan
+	pub fn step(
+		&self,
+		particle: &mut Particle,
+		leader: &Creature,
+		rng: &mut ThreadRng
+	) {
+		if !leader.same_topology(&particle.creature) &&
+		   rng.gen::<f32>() < ADOPT_RATE
+		{
+			particle.creature = leader.clone();
+			particle.best = leader.clone();
+			particle.velocity = random_velocity(leader.genes().len(), rng);
+		}
+
+		let mut genes = particle.creature.genes();
+		let best = particle.best.genes();
+
+		// A leader with a different body plan can't be followed, so the
+		// particle only heads towards its own best
+		let social = if leader.same_topology(&particle.creature) {
+			leader.genes()
+		} else {
+			genes.clone()
+		};
+
+		for idx in 0 .. genes.len() {
+			let velocity = self.inertia * particle.velocity[idx] +
+				self.cognitive * rng.gen::<f32>() * (best[idx] - genes[idx]) +
+				self.social * rng.gen::<f32>() * (social[idx] - genes[idx]);
+
+			particle.velocity[idx] =
+				velocity.max(-VELOCITY_MAX).min(VELOCITY_MAX);
+			genes[idx] += particle.velocity[idx];
+		}
+
+		particle.creature.genes_set(&genes);
+	}
+
+	/// Returns the index of the particle with the fittest best position
+	/// within the neighbourhood of the given particle
+	pub fn leader(&self, idx: usize) -> usize {
+		let len = self.particles.len();
+		let neighbours = match self.neighbourhood {
+			Some(radius) => {
+				let radius = cmp::min(radius, len / 2);
+				(len + idx - radius .. len + idx + radius + 1)
+					.map(|neighbour| neighbour % len)
+					.collect::<Vec<usize>>()
+			},
+			None => (0 .. len).collect::<Vec<usize>>()
+		};
+
+		neighbours.into_iter().fold(idx, |leader, neighbour| {
+			if self.particles[neighbour].best.fitness >
+			   self.particles[leader].best.fitness {
+				neighbour
+			} else {
+				leader
+			}
+		})
+	}
+}
+
+impl OptimisationMethod for ParticleSwarm {
+	fn generation_single(&mut self) -> GenResult {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+
+		if self.data.print { println!(
+			"PSO - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}",
+			self.data.gen,
+			self.data.generations[self.data.gen]
+			    .creatures[gen_size - 1]
+			    .fitness,
+			self.data.generations[self.data.gen].fitness_average(),
+			self.data.generations[self.data.gen].creatures[0].fitness
+		); }
+
+		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		// Every particle follows where its leader's best was at the start
+		// of the generation
+		let leaders = (0 .. self.particles.len())
+			.map(|idx| self.particles[self.leader(idx)].best.clone())
+			.collect::<Vec<Creature>>();
+
+		let mut particles = mem::replace(&mut self.particles, Vec::new());
+		{
+			let simulation = &self.data.simulation;
+			let cache = &self.data.cache;
+			let swarm = &*self;
+
+			particles
+			.par_iter_mut()
+			.zip(leaders.par_iter())
+			.for_each(|(particle, leader)| {
+				let mut rng_new = rand::thread_rng();
+				swarm.step(particle, leader, &mut rng_new);
+
+				cache.simulate(&mut particle.creature, simulation);
+
+				if particle.creature.fitness > particle.best.fitness {
+					particle.best = particle.creature.clone();
+				}
+			});
+		}
+		self.particles = particles;
+
+		let mut new_population = Population::empty(gen_size);
+		new_population.creatures = self.particles.iter()
+			.map(|particle| particle.creature.clone())
+			.collect();
+		new_population.sort_by_fittest();
+
+		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		self.data.generations.push(new_population);
+		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
+		self.data.gen += 1;
+
+		Ok(())
+	}
+
+	fn creature_get (&mut self, gen: usize, idx: usize) -> &mut Creature {
+		&mut self.data.generations[gen].creatures[idx]
+	}
+
+	fn get_data_mut(&mut self) -> &mut OpMethodData {
+		&mut self.data
+	}
+
+	fn get_data(&self) -> &OpMethodData {
+		&self.data
+	}
+
+	fn reset(&mut self) {
+		let mut rng = rand::thread_rng();
+		self.particles = self.data.generations[self.data.gen].creatures
+			.iter()
+			.map(|creature| Particle::new(creature, &mut rng))
+			.collect();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use physics::Simulation;
+	use population::Population;
+	use optimisationmethods::particle_swarm::ParticleSwarm;
+	use optimisationmethods::OptimisationMethod;
+
+	/// Run the generation function 10 times and make sure the average
+	/// population's fitness has increased, for both the whole swarm and a
+	/// ring of neighbours
+	#[test]
+	fn fitness_10gens() {
+		let mut rng = rand::thread_rng();
+
+		for &neighbourhood in &[None, Some(2)] {
+			let population = Population::new(100, &mut rng);
+			let mut pso = ParticleSwarm::new(population, false);
+			pso.neighbourhood = neighbourhood;
+
+			for _ in 0 .. 10 {
+				let _ = pso.generation_single();
+			}
+
+			let initial_fitness = pso.get_data().creature_get_average(0);
+			let final_fitness = pso.get_data().creature_get_average(10);
+
+			assert!(final_fitness > initial_fitness);
+		}
+	}
+
+	/// Make sure a particle's leader is the fittest best within its ring of
+	/// neighbours, wrapping around the ends of the swarm
+	#[test]
+	fn leader() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(10, &mut rng);
+		let mut pso = ParticleSwarm::new(population, false);
+		let _ = pso.generation_single();
+
+		for (idx, particle) in pso.particles.iter_mut().enumerate() {
+			particle.best.fitness = idx as f32;
+		}
+
+		pso.neighbourhood = Some(1);
+		assert_eq!(pso.leader(0), 9);
+		assert_eq!(pso.leader(4), 5);
+
+		pso.neighbourhood = None;
+		assert_eq!(pso.leader(0), 9);
+	}
+
+	/// Change the simulation part way through, making sure every particle's
+	/// best is the same as its recalculated creature rather than one found
+	/// under the old settings
+	#[test]
+	fn simulation_set() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(10, &mut rng);
+		let mut pso = ParticleSwarm::new(population, false);
+		let _ = pso.generation_single();
+
+		pso.simulation_set(Simulation::water());
+
+		let creatures = &pso.data.generations[pso.data.gen].creatures;
+		assert_eq!(pso.particles.len(), creatures.len());
+		for (particle, creature) in pso.particles.iter().zip(creatures) {
+			assert_eq!(particle.best.fitness, creature.fitness);
+		}
+	}
+}
@@ -0,0 +1,126 @@
+use population::Population;
+use creature::Creature;
+use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
+use rand;
+use time;
+use rayon::prelude::*;
+
+/// Random Search. Every generation is made of brand new random creatures,
+/// simulating as many of them as the genetic algorithm simulates children,
+/// with only the fittest creature found so far carried over. Any method
+/// which can't beat this is doing no better than chance.
+pub struct RandomSearch {
+	pub data: OpMethodData,
+
+	// Whether new creatures are given a neural controller, to match the
+	// starting population
+	pub controllers: bool
+}
+
+impl RandomSearch {
+	pub fn new(population: Population, print: bool) -> Box<RandomSearch> {
+		let controllers = population.creatures.iter()
+			.any(|creature| creature.has_controller());
+
+		Box::new(RandomSearch {
+			data: OpMethodData::new(vec![population], "RS".to_string(), print),
+			controllers: controllers
+		})
+	}
+}
+
+impl OptimisationMethod for RandomSearch {
+	fn generation_single(&mut self) -> GenResult {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+		let mut new_population = Population::empty(gen_size);
+
+		if self.data.print { println!(
+			"RS - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}",
+			self.data.gen,
+			self.data.generations[self.data.gen]
+			    .creatures[gen_size - 1]
+			    .fitness,
+			self.data.generations[self.data.gen].fitness_average(),
+			self.data.generations[self.data.gen].creatures[0].fitness
+		); }
+
+		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+		let controllers = self.controllers;
+
+		(0 .. gen_size)
+		.into_par_iter()
+		.map(|_| {
+			let mut rng_new = rand::thread_rng();
+			let mut creature = Creature::new(&mut rng_new);
+
+			if controllers {
+				creature.controller_add(&mut rng_new);
+			}
+
+			cache.simulate(&mut creature, simulation);
+			creature
+		})
+		.collect_into(&mut new_population.creatures);
+
+		// Keep the fittest creature found so far in place of the weakest new
+		// one, so the search never loses its best result
+		new_population.creatures.push(
+			self.data.generations[self.data.gen].creatures[0].clone()
+		);
+		new_population.sort_by_fittest();
+		new_population.creatures.truncate(gen_size);
+
+		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		self.data.generations.push(new_population);
+		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
+		self.data.gen += 1;
+
+		Ok(())
+	}
+
+	fn creature_get (&mut self, gen: usize, idx: usize) -> &mut Creature {
+		&mut self.data.generations[gen].creatures[idx]
+	}
+
+	fn get_data_mut(&mut self) -> &mut OpMethodData {
+		&mut self.data
+	}
+
+	fn get_data(&self) -> &OpMethodData {
+		&self.data
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use population::Population;
+	use optimisationmethods::random_search::RandomSearch;
+	use optimisationmethods::OptimisationMethod;
+
+	/// Run 10 generations and make sure the fittest creature never gets any
+	/// worse, and each generation stays the same size
+	#[test]
+	fn best_10gens() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(100, &mut rng);
+		let mut rs = RandomSearch::new(population, false);
+
+		for gen in 0 .. 10 {
+			let _ = rs.generation_single();
+
+			let data = rs.get_data();
+			assert_eq!(data.generations[gen + 1].creatures.len(), 100);
+			assert!(
+				data.creature_get_fittest(gen + 1).fitness >=
+				data.creature_get_fittest(gen).fitness
+			);
+		}
+	}
+}
@@ -3,7 +3,6 @@ use creature::Creature;
 use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
 use rand;
 use time;
-use physics;
 use rayon::prelude::*;
 
 pub const MUTABILITY_RATE: f32 = 1.0;
@@ -61,6 +60,9 @@ the current creatures any further".to_string()
 			));
 		}
 
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+
 		self.data.generations[self.data.gen].creatures
 		.par_iter_mut()
 		.map(|creature| {
@@ -72,7 +74,7 @@ the current creatures any further".to_string()
 				MUTABILITY_RATE * percentage as f32
 			);
 
-			physics::full_simulation_creature(&mut new_creature);
+			cache.simulate(&mut new_creature, simulation);
 
 			if new_creature.fitness > creature.fitness {
 				new_creature
@@ -92,6 +94,8 @@ the current creatures any further".to_string()
 		// method, and increase the generation number
 		self.data.generations.push(new_population);
 		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
 		self.data.gen += 1;
 
 		Ok(())
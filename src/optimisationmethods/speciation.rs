@@ -0,0 +1,275 @@
+use population::Population;
+use creature::Creature;
+use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
+use optimisationmethods::GroupStats;
+use optimisationmethods::genetic_algorithm::GeneticAlgorithm;
+use rand;
+use std::f32;
+use std::iter;
+use time;
+use rayon::prelude::*;
+
+/// Furthest a creature can be from a species' representative and still
+/// belong to it
+pub const COMPATIBILITY_THRESHOLD: f32 = 6.0;
+
+/// A group of creatures with similar body plans, which only breed with
+/// each other
+#[derive(Clone)]
+pub struct Species {
+	// Creature which others are compared to when deciding whether they
+	// belong to the species
+	pub representative: Creature,
+	pub members: Vec<Creature>
+}
+
+/// Genetic Algorithm with Speciation. Creatures are split into species by
+/// how compatible their body plans are, and share their fitness with the
+/// rest of their species, so a small species of new body plans is given as
+/// many children as its average fitness earns rather than being out-bred by
+/// the most common body plan before it has had time to improve.
+pub struct Speciation {
+	pub data: OpMethodData,
+	pub threshold: f32,
+
+	// Species of the creatures in the current generation
+	pub species: Vec<Species>
+}
+
+impl Speciation {
+	pub fn new(population: Population, print: bool) -> Box<Speciation> {
+		let mut sp = Speciation {
+			data: OpMethodData::new(vec![population], "SP".to_string(), print),
+			threshold: COMPATIBILITY_THRESHOLD,
+			species: Vec::new()
+		};
+		sp.reset();
+
+		Box::new(sp)
+	}
+
+	/// Places each creature in the first species whose representative it's
+	/// compatible with, starting a new species for any which aren't
+	pub fn speciate(&self, creatures: &[Creature]) -> Vec<Species> {
+		let mut species = self.species.iter()
+			.map(|species| Species {
+				representative: species.representative.clone(),
+				members: Vec::new()
+			})
+			.collect::<Vec<Species>>();
+
+		for creature in creatures {
+			let compatible = species.iter().position(|species| {
+				species.representative.compatibility(creature) < self.threshold
+			});
+
+			match compatible {
+				Some(idx) => species[idx].members.push(creature.clone()),
+				None => species.push(Species {
+					representative: creature.clone(),
+					members: vec![creature.clone()]
+				})
+			}
+		}
+
+		// Species without any creatures left have died out, and the rest are
+		// represented by their fittest member from now on
+		species.retain(|species| !species.members.is_empty());
+		for species in &mut species {
+			species.representative =
+				species.members.iter().max().unwrap().clone();
+		}
+
+		species
+	}
+
+	/// Returns how many children each species has, in proportion to the sum
+	/// of its members' fitness once shared between the species
+	pub fn offspring_counts(&self, gen_size: usize) -> Vec<usize> {
+		// Fitness is shifted to be positive so it can be shared out
+		let lowest = self.species.iter()
+			.flat_map(|species| species.members.iter())
+			.fold(f32::MAX, |lowest, creature| lowest.min(creature.fitness));
+
+		let shares = self.species.iter()
+			.map(|species| {
+				species.members.iter().fold(0.0, |sum, creature| {
+					sum + creature.fitness - lowest + 1.0
+				}) / species.members.len() as f32
+			})
+			.collect::<Vec<f32>>();
+		let total = shares.iter().fold(0.0, |sum, share| sum + share);
+
+		let exact = shares.iter()
+			.map(|share| share / total * gen_size as f32)
+			.collect::<Vec<f32>>();
+		let mut counts = exact.iter()
+			.map(|count| count.floor() as usize)
+			.collect::<Vec<usize>>();
+
+		// Whatever is left after rounding down goes to the species which
+		// lost the most by it
+		let mut order = (0 .. counts.len()).collect::<Vec<usize>>();
+		order.sort_by(|&a, &b| {
+			(exact[b] - counts[b] as f32)
+				.partial_cmp(&(exact[a] - counts[a] as f32))
+				.unwrap()
+		});
+
+		let assigned = counts.iter().fold(0, |sum, count| sum + count);
+		for &idx in order.iter().take(gen_size.saturating_sub(assigned)) {
+			counts[idx] += 1;
+		}
+
+		counts
+	}
+
+	/// Breeds each species' share of the children from its own members
+	fn offspring(&self, gen_size: usize) -> Vec<Creature> {
+		let species = &self.species;
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+		let mut offspring = Vec::with_capacity(gen_size);
+
+		let parents = self.offspring_counts(gen_size).into_iter()
+			.enumerate()
+			.flat_map(|(idx, count)| iter::repeat(idx).take(count))
+			.collect::<Vec<usize>>();
+
+		parents
+		.into_par_iter()
+		.map(|idx| {
+			let mut rng = rand::thread_rng();
+			let members = &species[idx].members;
+			GeneticAlgorithm::breed(members, simulation, cache, &mut rng)
+		})
+		.collect_into(&mut offspring);
+
+		offspring
+	}
+}
+
+impl OptimisationMethod for Speciation {
+	fn generation_single(&mut self) -> GenResult {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+		let mut new_population = Population::empty(gen_size);
+
+		if self.data.print { println!(
+			"SP - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}\tSpecies: {}",
+			self.data.gen,
+			self.data.generations[self.data.gen]
+			    .creatures[gen_size - 1]
+			    .fitness,
+			self.data.generations[self.data.gen].fitness_average(),
+			self.data.generations[self.data.gen].creatures[0].fitness,
+			self.species.len()
+		); }
+
+		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		new_population.creatures = self.offspring(gen_size);
+		new_population.sort_by_fittest();
+
+		let species = self.speciate(&new_population.creatures);
+		self.species = species;
+
+		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		self.data.groups.push(
+			self.species.iter()
+				.map(|species| GroupStats::new(&species.members))
+				.collect()
+		);
+
+		self.data.generations.push(new_population);
+		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
+		self.data.gen += 1;
+
+		Ok(())
+	}
+
+	fn creature_get (&mut self, gen: usize, idx: usize) -> &mut Creature {
+		&mut self.data.generations[gen].creatures[idx]
+	}
+
+	fn get_data_mut(&mut self) -> &mut OpMethodData {
+		&mut self.data
+	}
+
+	fn get_data(&self) -> &OpMethodData {
+		&self.data
+	}
+
+	fn reset(&mut self) {
+		self.species.clear();
+		let species =
+			self.speciate(&self.data.generations[self.data.gen].creatures);
+		self.species = species;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use creature::Creature;
+	use population::Population;
+	use optimisationmethods::speciation::Speciation;
+	use optimisationmethods::OptimisationMethod;
+
+	/// Run the generation function 10 times and make sure the average
+	/// population's fitness has increased, with every creature counted in
+	/// one of the species
+	#[test]
+	fn fitness_10gens() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(100, &mut rng);
+		let mut sp = Speciation::new(population, false);
+
+		for _ in 0 .. 10 {
+			let _ = sp.generation_single();
+		}
+
+		let initial_fitness = sp.get_data().creature_get_average(0);
+		let final_fitness = sp.get_data().creature_get_average(10);
+		assert!(final_fitness > initial_fitness);
+
+		assert_eq!(sp.get_data().groups.len(), 10);
+		for stats in &sp.get_data().groups {
+			assert_eq!(stats.iter().fold(0, |sum, s| sum + s.size), 100);
+		}
+	}
+
+	/// Make a population of copies of one creature with a single creature of
+	/// another body plan, then make sure the newcomer is given a species of
+	/// its own, which earns as many children as its fitness deserves
+	#[test]
+	fn speciate() {
+		let mut rng = rand::thread_rng();
+		let seed = Creature::new(&mut rng);
+		let mut newcomer = seed.clone();
+		newcomer.nodes.push(Creature::add_node_random(&mut rng));
+		let muscle = Creature::add_muscle_random(&newcomer.nodes, &mut rng);
+		newcomer.muscles.push(muscle);
+
+		let mut population = Population::empty(10);
+		for _ in 0 .. 9 {
+			population.creatures.push(seed.clone());
+		}
+		population.creatures.push(newcomer);
+		for creature in &mut population.creatures {
+			creature.fitness = 10.0;
+		}
+
+		// A single new node and muscle is a distance of 2 away
+		let mut sp = Speciation::new(population.clone(), false);
+		sp.threshold = 1.5;
+		sp.reset();
+		assert_eq!(sp.species.len(), 2);
+		assert_eq!(sp.species[0].members.len(), 9);
+		assert_eq!(sp.species[1].members.len(), 1);
+
+		assert_eq!(sp.offspring_counts(10), vec![5, 5]);
+	}
+}
@@ -0,0 +1,450 @@
+use population::Population;
+use creature::{self, Creature};
+use physics;
+use optimisationmethods::{GenResult, OptimisationMethod, OpMethodData};
+use rand::{self, Rng, ThreadRng};
+use std::collections::VecDeque;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use time;
+use rayon::prelude::*;
+
+pub const MUTABILITY_RATE: f32 = 0.05;
+
+/// Number of neighbours simulated for each creature every generation
+pub const CANDIDATES: usize = 4;
+
+/// Number of moves something stays on a creature's tabu list for
+pub const TENURE: usize = 5;
+
+/// Furthest each gene of a moved node or muscle can change, as a fraction
+/// of its bounds
+pub const MOVE_STEP: f32 = 0.1;
+
+/// Width of the cells each gene is rounded into when remembering genomes, as
+/// a fraction of its bounds
+pub const REGION_SIZE: f32 = 0.25;
+
+/// What a creature remembers about where it has been
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Memory {
+	/// The regions of genomes it has visited, which it can't go back to
+	Genomes,
+
+	/// The nodes and muscles it has changed, which it can't change again
+	Moves
+}
+
+/// A single entry on a tabu list
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Attribute {
+	Genome(u64),
+	Node(usize),
+	Muscle(usize)
+}
+
+/// The search each creature carries out, kept alongside it between
+/// generations
+#[derive(Clone, Debug)]
+pub struct Walker {
+	pub tabu: VecDeque<Attribute>,
+
+	// Highest fitness the creature has had, which a tabu move has to beat
+	pub best: f32
+}
+
+impl Walker {
+	/// Returns whether a move with the given attribute, leading to the given
+	/// fitness, can be made
+	pub fn allows(&self, attribute: &Attribute, fitness: f32) -> bool {
+		!self.tabu.contains(attribute) || fitness > self.best
+	}
+}
+
+/// Tabu Search. Every creature moves to its fittest neighbour each
+/// generation, even when that's downhill, but can't make a move which is on
+/// its tabu list of recent ones. A tabu move is still allowed if it leads
+/// somewhere fitter than the creature has ever been (aspiration).
+pub struct TabuSearch {
+	pub data: OpMethodData,
+	pub memory: Memory,
+	pub tenure: usize,
+
+	// Search of each creature in the current generation, in the same order
+	// as its creatures
+	pub walkers: Vec<Walker>,
+
+	// Average tabu list length and number of tabu moves allowed through
+	// aspiration, for every generation
+	pub tabu_size: Vec<f32>,
+	pub aspiration_hits: Vec<usize>
+}
+
+impl TabuSearch {
+	pub fn new(
+		population: Population,
+		print: bool,
+		memory: Memory
+	) -> Box<TabuSearch> {
+		let mut ts = TabuSearch {
+			data: OpMethodData::new(vec![population], "TS".to_string(), print),
+			memory: memory,
+			tenure: TENURE,
+			walkers: Vec::new(),
+			tabu_size: Vec::new(),
+			aspiration_hits: Vec::new()
+		};
+		ts.reset();
+
+		Box::new(ts)
+	}
+
+	/// Returns the average length of the given searches' tabu lists
+	fn tabu_average(walkers: &[Walker]) -> f32 {
+		if walkers.is_empty() { return 0.0 }
+		walkers.iter().fold(0, |sum, walker| sum + walker.tabu.len())
+			as f32 / walkers.len() as f32
+	}
+
+	/// Returns a hash of the region of genomes a creature is in. Every
+	/// mutation moves each gene a little, so genes are rounded into cells of
+	/// `REGION_SIZE` first, letting nearby genomes count as the same place.
+	pub fn region(creature: &Creature) -> u64 {
+		let mut hasher = DefaultHasher::new();
+
+		creature.nodes.len().hash(&mut hasher);
+		for gene in creature.genes() {
+			((gene / REGION_SIZE) as u32).hash(&mut hasher);
+		}
+
+		for muscle in &creature.muscles {
+			muscle.nodes.0.hash(&mut hasher);
+			muscle.nodes.1.hash(&mut hasher);
+			(muscle.waveform as u8).hash(&mut hasher);
+		}
+
+		hasher.finish()
+	}
+
+	/// Makes a neighbour of a creature, along with the attribute which makes
+	/// it tabu
+	pub fn neighbour(
+		&self,
+		creature: &Creature,
+		rng: &mut ThreadRng
+	) -> (Creature, Attribute) {
+		match self.memory {
+			Memory::Genomes => {
+				let neighbour =
+					OpMethodData::mutate(creature, rng, MUTABILITY_RATE);
+				let region = TabuSearch::region(&neighbour);
+				(neighbour, Attribute::Genome(region))
+			},
+			Memory::Moves => {
+				let nodes = creature.nodes.len();
+				let idx = rng.gen_range(0, nodes + creature.muscles.len());
+				let (attribute, genes) = if idx < nodes {
+					let start = idx * creature::GENES_NODE;
+					(
+						Attribute::Node(idx),
+						start .. start + creature::GENES_NODE
+					)
+				} else {
+					let start = nodes * creature::GENES_NODE +
+						(idx - nodes) * creature::GENES_MUSCLE;
+					(
+						Attribute::Muscle(idx - nodes),
+						start .. start + creature::GENES_MUSCLE
+					)
+				};
+
+				let mut values = creature.genes();
+				for gene in genes {
+					values[gene] = physics::clamp(
+						values[gene] + rng.gen_range(-MOVE_STEP, MOVE_STEP),
+						0.0 .. 1.0
+					);
+				}
+
+				let mut neighbour = creature.clone();
+				neighbour.genes_set(&values);
+				(neighbour, attribute)
+			}
+		}
+	}
+
+	/// Moves a creature to its fittest allowed neighbour, returning where it
+	/// ends up, its updated search and whether aspiration allowed the move
+	pub fn step(
+		&self,
+		creature: &Creature,
+		walker: &Walker,
+		rng: &mut ThreadRng
+	) -> (Creature, Walker, bool) {
+		let simulation = &self.data.simulation;
+		let cache = &self.data.cache;
+
+		let mut fittest: Option<(Creature, Attribute, bool)> = None;
+		for _ in 0 .. CANDIDATES {
+			let (mut neighbour, attribute) = self.neighbour(creature, rng);
+			cache.simulate(&mut neighbour, simulation);
+
+			if !walker.allows(&attribute, neighbour.fitness) {
+				continue;
+			}
+			let tabu = walker.tabu.contains(&attribute);
+
+			let fitter = fittest.as_ref()
+				.map_or(true, |fittest| neighbour.fitness > fittest.0.fitness);
+			if fitter {
+				fittest = Some((neighbour, attribute, tabu));
+			}
+		}
+
+		let (neighbour, attribute, aspiration) = match fittest {
+			Some(fittest) => fittest,
+			None => return (creature.clone(), walker.clone(), false)
+		};
+
+		let mut walker = walker.clone();
+		walker.tabu.push_back(attribute);
+		while walker.tabu.len() > self.tenure {
+			walker.tabu.pop_front();
+		}
+		walker.best = walker.best.max(neighbour.fitness);
+
+		(neighbour, walker, aspiration)
+	}
+
+	/// Moves every creature in the current generation a single step
+	fn offspring(&self) -> Vec<(Creature, Walker, bool)> {
+		let creatures = &self.data.generations[self.data.gen].creatures;
+		let mut offspring = Vec::with_capacity(creatures.len());
+
+		creatures
+		.par_iter()
+		.zip(self.walkers.par_iter())
+		.map(|(creature, walker)| {
+			let mut rng_new = rand::thread_rng();
+			self.step(creature, walker, &mut rng_new)
+		})
+		.collect_into(&mut offspring);
+
+		offspring
+	}
+}
+
+impl OptimisationMethod for TabuSearch {
+	fn generation_single(&mut self) -> GenResult {
+		let gen_size = self.data.generations[self.data.gen].creatures.len();
+
+		if self.data.print { println!(
+			"TS - Gen {}: Lowest Fit: {}\tAverage Fit: {}\tHighest Fit: {}\tTabu Size: {}\tAspiration Hits: {}",
+			self.data.gen,
+			self.data.generations[self.data.gen]
+			    .creatures[gen_size - 1]
+			    .fitness,
+			self.data.generations[self.data.gen].fitness_average(),
+			self.data.generations[self.data.gen].creatures[0].fitness,
+			self.tabu_size[self.data.gen],
+			self.aspiration_hits[self.data.gen]
+		); }
+
+		let time_start = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		let mut offspring = self.offspring();
+		offspring.sort_by(|a, b| b.0.cmp(&a.0));
+
+		let aspiration_hits = offspring.iter()
+			.filter(|&&(_, _, aspiration)| aspiration)
+			.count();
+
+		let mut new_population = Population::empty(gen_size);
+		let mut walkers = Vec::with_capacity(gen_size);
+		for (creature, walker, _) in offspring {
+			new_population.creatures.push(creature);
+			walkers.push(walker);
+		}
+
+		let time_end = time::precise_time_ns() as f32 / 1_000_000.0;
+
+		self.tabu_size.push(TabuSearch::tabu_average(&walkers));
+		self.aspiration_hits.push(aspiration_hits);
+		self.walkers = walkers;
+
+		self.data.generations.push(new_population);
+		self.data.gen_time.push(time_end - time_start);
+		let hit_rate = self.data.cache.hit_rate_take();
+		self.data.cache_hit_rate.push(hit_rate);
+		self.data.gen += 1;
+
+		Ok(())
+	}
+
+	fn creature_get (&mut self, gen: usize, idx: usize) -> &mut Creature {
+		&mut self.data.generations[gen].creatures[idx]
+	}
+
+	fn get_data_mut(&mut self) -> &mut OpMethodData {
+		&mut self.data
+	}
+
+	fn get_data(&self) -> &OpMethodData {
+		&self.data
+	}
+
+	fn tabu_size(&self, gen: usize) -> Option<f32> {
+		self.tabu_size.get(gen).cloned()
+	}
+
+	fn aspiration_hits(&self, gen: usize) -> Option<usize> {
+		self.aspiration_hits.get(gen).cloned()
+	}
+
+	fn reset(&mut self) {
+		let memory = self.memory;
+		self.walkers = self.data.generations[self.data.gen].creatures
+			.iter()
+			.map(|creature| {
+				let mut tabu = VecDeque::new();
+				if memory == Memory::Genomes {
+					let region = TabuSearch::region(creature);
+					tabu.push_back(Attribute::Genome(region));
+				}
+
+				Walker {
+					tabu: tabu,
+					best: creature.fitness
+				}
+			})
+			.collect();
+
+		let gen = self.data.gen;
+		self.tabu_size.truncate(gen);
+		self.tabu_size.push(TabuSearch::tabu_average(&self.walkers));
+		self.aspiration_hits.truncate(gen);
+		self.aspiration_hits.push(0);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand;
+	use std::f32;
+	use physics::Simulation;
+	use population::Population;
+	use optimisationmethods::tabu_search::{Attribute, Memory, TabuSearch};
+	use optimisationmethods::OptimisationMethod;
+
+	/// Run 10 generations with each kind of memory and make sure a fitter
+	/// creature was found than any of the starting ones, and no tabu list
+	/// grew longer than its tenure
+	#[test]
+	fn fitness_10gens() {
+		let mut rng = rand::thread_rng();
+
+		for &memory in &[Memory::Genomes, Memory::Moves] {
+			let population = Population::new(100, &mut rng);
+			let mut ts = TabuSearch::new(population, false, memory);
+
+			for _ in 0 .. 10 {
+				let _ = ts.generation_single();
+			}
+
+			let initial_fitness = ts.get_data().creature_get_fittest(0).fitness;
+			assert!(ts.get_data().generations_get_fittest() > initial_fitness);
+
+			assert_eq!(ts.tabu_size.len(), 11);
+			assert_eq!(ts.aspiration_hits.len(), 11);
+			for walker in &ts.walkers {
+				assert!(walker.tabu.len() <= ts.tenure);
+			}
+		}
+	}
+
+	/// Make every move tabu, then make sure a creature only moves when its
+	/// neighbours are fitter than it has ever been
+	#[test]
+	fn aspiration() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(10, &mut rng);
+		let mut ts = TabuSearch::new(population, false, Memory::Moves);
+		let _ = ts.generation_single();
+
+		let creature = ts.get_data().creature_get_fittest(1).clone();
+		let mut walker = ts.walkers[0].clone();
+		walker.tabu.clear();
+		for idx in 0 .. creature.nodes.len() {
+			walker.tabu.push_back(Attribute::Node(idx));
+		}
+		for idx in 0 .. creature.muscles.len() {
+			walker.tabu.push_back(Attribute::Muscle(idx));
+		}
+
+		walker.best = f32::MAX;
+		let (stayed, _, aspiration) = ts.step(&creature, &walker, &mut rng);
+		assert!(!aspiration);
+		assert_eq!(stayed.genes(), creature.genes());
+
+		walker.best = f32::MIN;
+		let (moved, moved_walker, aspiration) =
+			ts.step(&creature, &walker, &mut rng);
+		assert!(aspiration);
+		assert!(moved.genes() != creature.genes());
+		assert_eq!(moved_walker.best, moved.fitness);
+	}
+
+	/// Change the simulation part way through, making sure every search's
+	/// best is its recalculated creature's fitness rather than one found
+	/// under the old settings
+	#[test]
+	fn simulation_set() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(10, &mut rng);
+		let mut ts = TabuSearch::new(population, false, Memory::Moves);
+		let _ = ts.generation_single();
+
+		ts.simulation_set(Simulation::water());
+
+		let creatures = &ts.data.generations[ts.data.gen].creatures;
+		assert_eq!(ts.walkers.len(), creatures.len());
+		for (walker, creature) in ts.walkers.iter().zip(creatures) {
+			assert_eq!(walker.best, creature.fitness);
+			assert!(walker.tabu.is_empty());
+		}
+		assert_eq!(ts.tabu_size(1), Some(0.0));
+		assert_eq!(ts.aspiration_hits(1), Some(0));
+	}
+	/// Keep making neighbours until one lands back in its creature's own
+	/// region, then make sure the creature's search won't move there unless
+	/// it's fitter than the creature has ever been
+	#[test]
+	fn revisit() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(10, &mut rng);
+		let ts = TabuSearch::new(population, false, Memory::Genomes);
+		let creatures = &ts.data.generations[0].creatures;
+
+		// Most mutations change a muscle completely, so some creatures very
+		// rarely end up back where they were
+		let mut revisit = None;
+		for (creature, walker) in creatures.iter().zip(&ts.walkers) {
+			let region = Attribute::Genome(TabuSearch::region(creature));
+			assert_eq!(walker.tabu.front(), Some(&region));
+
+			let found = (0 .. 1000).any(|_| {
+				ts.neighbour(creature, &mut rng).1 == region
+			});
+			if found {
+				revisit = Some((walker.clone(), region));
+				break;
+			}
+		}
+		let (mut walker, attribute) = revisit.expect("No neighbour revisited");
+
+		walker.best = f32::MAX;
+		assert!(!walker.allows(&attribute, 0.0));
+		walker.best = f32::MIN;
+		assert!(walker.allows(&attribute, 0.0));
+	}
+}
@@ -1,74 +1,699 @@
-use creature::{self, Creature, Node};
+use creature::{self, Creature, Node, Muscle, Waveform};
+use controller::Sensors;
 use population::Population;
+use std::cmp;
+use std::f32;
+use rand::{self, Rng, ThreadRng};
+use std::f32::consts::PI;
 use std::ops::Range;
 
 pub const GRAVITY: f32 = 0.3;
 pub const RESISTANCE: f32 = 0.97;
 pub const SIM_LENGTH: u32 = 900; // 60 frames per second for 15 seconds
 
-pub fn full_simulation_population(population: &mut Population) {
+/// Drag applied by water to each unit of a muscle's length, for movement
+/// across the muscle and along it. Pushing sideways through the water is much
+/// harder than sliding through it, which is what makes swimming possible.
+pub const DRAG_NORMAL: f32 = 0.002;
+pub const DRAG_TANGENT: f32 = 0.0002;
+
+/// Most of a muscle's velocity that drag can remove in a single frame, so
+/// that very long muscles don't bounce back
+const DRAG_MAX: f32 = 0.4;
+
+/// Number of times a creature's position is recorded during a simulation, to
+/// describe the way it moved
+pub const BEHAVIOUR_SAMPLES: usize = 8;
+
+/// Settings used when running a creature's full simulation
+#[derive(Clone)]
+pub struct Simulation {
+	pub length: u32,
+	pub exit_rules: Vec<ExitRule>,
+	pub forces: Vec<ForceField>,
+	pub environment: Environment,
+
+	// How steep the floor is, where a positive slope rises to the right
+	pub slope: f32,
+
+	// Move each creature down onto the ground before it is simulated, rather
+	// than letting it fall there once the simulation begins
+	pub ground_start: bool,
+
+	// Number of frames each creature is given to settle, with its muscles
+	// relaxed, before the timed simulation begins
+	pub settle_frames: u32,
+
+	// Simulate each creature several times with small random changes, using
+	// its average fitness, instead of running a single simulation
+	pub perturbation: Option<Perturbation>,
+
+	// Take away some fitness for every node and muscle a creature has
+	pub parsimony: Option<Parsimony>
+}
+
+/// Fitness taken away for each node and muscle, so that simpler creatures are
+/// preferred over bloated ones that walk just as far
+#[derive(Clone, Copy, Debug)]
+pub struct Parsimony {
+	pub node: f32,
+	pub muscle: f32
+}
+
+/// Small random changes made to a creature and its world each time it is
+/// simulated, so creatures which only work from one exact starting position
+/// are not favoured
+#[derive(Clone)]
+pub struct Perturbation {
+	// Number of times each creature is simulated
+	pub evaluations: u32,
+
+	// Furthest distance each node's starting position can be moved
+	pub jitter: f32,
+
+	// Largest fraction each node's friction can be changed by
+	pub friction: f32,
+
+	// Steepest slope the floor can be given, uphill or downhill
+	pub slope: f32
+}
+
+/// Summary of a creature's fitness over several perturbed simulations
+#[derive(Clone, Copy, Debug)]
+pub struct FitnessStats {
+	pub mean: f32,
+	pub min: f32,
+	pub variance: f32
+}
+
+/// The world creatures are simulated in
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Environment {
+	/// Creatures fall under gravity and push themselves along the floor
+	Land,
+
+	/// Creatures float with neutral buoyancy, without a floor, and swim by
+	/// pushing against the water with their muscles
+	Water
+}
+
+/// Rules for ending a creature's simulation early, once running the rest of
+/// it would not change the outcome
+#[derive(Clone)]
+pub enum ExitRule {
+	/// The creature has moved less than `distance` in the last `frames`
+	/// frames, so its current fitness is kept
+	Motionless { frames: u32, distance: f32 },
+
+	/// A node has left the given area, so its current fitness is kept
+	OutOfBounds { x: Range<f32>, y: Range<f32> },
+
+	/// A muscle has stretched past this many times its extended length, or a
+	/// node's position is no longer a number, so it keeps the fitness it
+	/// started with
+	Exploded(f32)
+}
+
+/// External forces pushing on every node as well as gravity, such as wind
+#[derive(Clone)]
+pub enum ForceField {
+	/// The same force everywhere, on every frame
+	Uniform { x: f32, y: f32 },
+
+	/// A force which builds up from nothing to full strength and back again
+	/// over every `period` frames
+	Gust { x: f32, y: f32, period: u32 },
+
+	/// A force which only pushes on nodes inside the given area
+	Zone { area_x: Range<f32>, area_y: Range<f32>, x: f32, y: f32 }
+}
+
+/// Describes how a creature moved during its last simulation, rather than
+/// only how far it went
+#[derive(Clone, Debug, Default)]
+pub struct Behaviour {
+	// The creature's centre at evenly spaced frames of the simulation,
+	// relative to where it started. The last one is where it finished.
+	pub trace: Vec<(f32, f32)>,
+
+	// Fraction of the time the creature's nodes spent touching the ground
+	pub ground_contact: f32,
+
+	// Highest the creature's lowest node got above the floor after first
+	// landing on it, so falling from where it started doesn't count
+	pub height: f32
+}
+
+impl Simulation {
+	/// Returns settings that end a creature's simulation early if it stops
+	/// moving, leaves the world, or explodes
+	pub fn early_exit() -> Simulation {
+		Simulation {
+			length: SIM_LENGTH,
+			exit_rules: vec![
+				ExitRule::Motionless { frames: 120, distance: 2.0 },
+				ExitRule::OutOfBounds {
+					x: -16384.0 .. 16384.0,
+					y: -16384.0 .. creature::BOUNDS_NODE_Y.end + 16384.0
+				},
+				ExitRule::Exploded(4.0)
+			],
+			forces: Vec::new(),
+			environment: Environment::Land,
+			slope: 0.0,
+			ground_start: false,
+			settle_frames: 0,
+			perturbation: None,
+			parsimony: None
+		}
+	}
+
+	/// Returns settings that simulate each creature the given number of times
+	/// with small random changes, using its average fitness
+	pub fn robust(evaluations: u32) -> Simulation {
+		Simulation {
+			perturbation: Some(Perturbation::new(evaluations)),
+			.. Simulation::default()
+		}
+	}
+
+	/// Returns the height of the floor at the given X position. Slopes are
+	/// measured from the middle of where creatures start, so the floor
+	/// underneath them starts at its usual height.
+	pub fn floor(&self, x: f32) -> f32 {
+		creature::BOUNDS_NODE_Y.end -
+		(x - creature::BOUNDS_NODE_X.end / 2.0) * self.slope
+	}
+
+	/// Returns settings for creatures swimming through water
+	pub fn water() -> Simulation {
+		Simulation {
+			environment: Environment::Water,
+			.. Simulation::default()
+		}
+	}
+
+	/// Returns settings with a constant wind blowing against the direction
+	/// creatures are trying to move, so they have to walk upwind
+	pub fn headwind(strength: f32) -> Simulation {
+		Simulation {
+			forces: vec![ForceField::Uniform { x: -strength, y: 0.0 }],
+			.. Simulation::default()
+		}
+	}
+}
+
+impl Default for Simulation {
+	fn default() -> Simulation {
+		Simulation {
+			length: SIM_LENGTH,
+			exit_rules: Vec::new(),
+			forces: Vec::new(),
+			environment: Environment::Land,
+			slope: 0.0,
+			ground_start: false,
+			settle_frames: 0,
+			perturbation: None,
+			parsimony: None
+		}
+	}
+}
+
+impl ExitRule {
+	/// Returns whether the rule says the simulation should end after the
+	/// given frame. `checkpoint` holds the creature's position the last time
+	/// this rule was checked.
+	pub fn triggered(
+		&self,
+		step: u32,
+		creature: &Creature,
+		checkpoint: &mut f32
+	) -> bool {
+		match *self {
+			ExitRule::Motionless { frames, distance } => {
+				if (step + 1) % cmp::max(frames, 1) != 0 { return false; }
+
+				let position = creature.fitness();
+				let moved = (position - *checkpoint).abs();
+				*checkpoint = position;
+				moved < distance
+			},
+			ExitRule::OutOfBounds { ref x, ref y } => {
+				creature.nodes.iter().any(|node| {
+					node.x < x.start || node.x > x.end ||
+					node.y < y.start || node.y > y.end
+				})
+			},
+			ExitRule::Exploded(ratio) => {
+				creature.nodes.iter().any(|node| {
+					!node.x.is_finite() || !node.y.is_finite()
+				}) ||
+				creature.muscles.iter().any(|muscle| {
+					let (a, b) = creature.get_nodes(&muscle.nodes);
+					a.distance(b) > muscle.len_max * ratio
+				})
+			}
+		}
+	}
+}
+
+impl Perturbation {
+	/// Returns perturbations of a typical size for the given number of
+	/// evaluations
+	pub fn new(evaluations: u32) -> Perturbation {
+		Perturbation {
+			evaluations: evaluations,
+			jitter: 4.0,
+			friction: 0.1,
+			slope: 0.05
+		}
+	}
+
+	/// Randomly moves each of the creature's nodes' starting position and
+	/// changes their friction, returning a random slope for the floor
+	pub fn apply(&self, creature: &mut Creature, rng: &mut ThreadRng) -> f32 {
+		for node in &mut creature.nodes {
+			node.start_x += rng.gen_range(-1.0, 1.0) * self.jitter;
+			node.start_y += rng.gen_range(-1.0, 1.0) * self.jitter;
+			let change = rng.gen_range(-1.0, 1.0) * self.friction;
+			node.friction = clamp(node.friction * (1.0 + change), 0.0 .. 1.0);
+		}
+
+		rng.gen_range(-1.0, 1.0) * self.slope
+	}
+}
+
+impl Parsimony {
+	/// Returns a penalty with the same weight for nodes and muscles
+	pub fn new(weight: f32) -> Parsimony {
+		Parsimony {
+			node: weight,
+			muscle: weight
+		}
+	}
+
+	/// Returns how much fitness the creature loses for its complexity
+	pub fn penalty(&self, creature: &Creature) -> f32 {
+		creature.nodes.len() as f32 * self.node +
+		creature.muscles.len() as f32 * self.muscle
+	}
+}
+
+impl Behaviour {
+	/// Returns how differently two creatures moved, as the distance between
+	/// their traces
+	pub fn distance(&self, other: &Behaviour) -> f32 {
+		self.trace.iter().zip(other.trace.iter())
+			.fold(0.0, |sum, (a, b)| {
+				sum + (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)
+			})
+			.sqrt()
+	}
+
+	/// Returns the average of several behaviours, each with the same number
+	/// of samples in their trace
+	pub fn average(behaviours: &[Behaviour]) -> Behaviour {
+		let len = behaviours.len() as f32;
+		let mut average = Behaviour {
+			trace: vec![(0.0, 0.0); BEHAVIOUR_SAMPLES],
+			ground_contact: 0.0,
+			height: 0.0
+		};
+
+		for behaviour in behaviours {
+			for (sum, sample) in average.trace.iter_mut()
+				.zip(behaviour.trace.iter()) {
+				sum.0 += sample.0 / len;
+				sum.1 += sample.1 / len;
+			}
+			average.ground_contact += behaviour.ground_contact / len;
+			average.height += behaviour.height / len;
+		}
+
+		average
+	}
+}
+
+impl FitnessStats {
+	/// Works out the mean, minimum and variance of a set of fitness values
+	pub fn new(fitness: &[f32]) -> FitnessStats {
+		let len = fitness.len() as f32;
+		let mean = fitness.iter().fold(0.0, |sum, f| sum + f) / len;
+
+		FitnessStats {
+			mean: mean,
+			min: fitness.iter().cloned().fold(f32::INFINITY, f32::min),
+			variance: fitness.iter()
+				.fold(0.0, |sum, f| sum + (f - mean).powi(2)) / len
+		}
+	}
+}
+
+impl ForceField {
+	/// Returns the force pushing on a node during the given frame
+	pub fn force(&self, timer: u32, node: &Node) -> (f32, f32) {
+		match *self {
+			ForceField::Uniform { x, y } => (x, y),
+			ForceField::Gust { x, y, period } => {
+				let period = cmp::max(period, 1);
+				let cycle = (timer % period) as f32 / period as f32;
+				let strength = (1.0 - (cycle * PI * 2.0).cos()) * 0.5;
+				(x * strength, y * strength)
+			},
+			ForceField::Zone { ref area_x, ref area_y, x, y } => {
+				if node.x >= area_x.start && node.x < area_x.end &&
+				   node.y >= area_y.start && node.y < area_y.end {
+					(x, y)
+				} else {
+					(0.0, 0.0)
+				}
+			}
+		}
+	}
+}
+
+pub fn full_simulation_population(
+	population: &mut Population,
+	simulation: &Simulation
+) {
 	for creature in &mut population.creatures {
-		full_simulation_creature(creature);
+		full_simulation_creature(creature, simulation);
+	}
+}
+
+/// Runs a creature's simulation and calculates its fitness, returning the
+/// number of frames that were simulated before it finished. If the settings
+/// ask for perturbed simulations, the creature is simulated several times
+/// and given its average fitness, and any parsimony penalty is taken away
+/// afterwards.
+pub fn full_simulation_creature(
+	creature: &mut Creature,
+	simulation: &Simulation
+) -> u32 {
+	let frames = match simulation.perturbation {
+		Some(ref perturbation) => {
+			perturbed_simulation_creature(creature, simulation, perturbation)
+		},
+		None => {
+			creature.fitness_stats = None;
+			single_simulation_creature(creature, simulation)
+		}
+	};
+
+	if let Some(ref parsimony) = simulation.parsimony {
+		creature.fitness -= parsimony.penalty(creature);
 	}
+
+	frames
 }
 
-pub fn full_simulation_creature(creature: &mut Creature) {
+/// Simulates a creature several times, each time with random changes to its
+/// starting position, friction and the slope of the floor. The creature's
+/// fitness is the mean of every simulation, and the spread of the results is
+/// kept in its `fitness_stats`. Returns the total number of frames simulated.
+pub fn perturbed_simulation_creature(
+	creature: &mut Creature,
+	simulation: &Simulation,
+	perturbation: &Perturbation
+) -> u32 {
+	let mut rng = rand::thread_rng();
+	let mut fitness = Vec::with_capacity(perturbation.evaluations as usize);
+	let mut energy = 0.0;
+	let mut behaviours = Vec::with_capacity(perturbation.evaluations as usize);
+	let mut frames = 0;
+
+	let mut world = simulation.clone();
+	world.perturbation = None;
+
+	for _ in 0 .. cmp::max(perturbation.evaluations, 1) {
+		let mut trial = creature.clone();
+		let slope = perturbation.apply(&mut trial, &mut rng);
+		world.slope = simulation.slope + slope;
+
+		frames += single_simulation_creature(&mut trial, &world);
+		fitness.push(trial.fitness);
+		energy += trial.energy;
+		behaviours.push(trial.behaviour);
+	}
+
+	let stats = FitnessStats::new(&fitness);
+	creature.fitness = stats.mean;
+	creature.energy = energy / fitness.len() as f32;
+	creature.behaviour = Behaviour::average(&behaviours);
+	creature.fitness_stats = Some(stats);
 	creature.reset_position();
-	for step in 0 .. SIM_LENGTH {
-		simulation_step(step, creature);
+
+	frames
+}
+
+/// Runs a single simulation of a creature exactly as it is, and calculates
+/// its fitness, the energy it used and how it moved, returning the number of
+/// frames that were simulated
+fn single_simulation_creature(
+	creature: &mut Creature,
+	simulation: &Simulation
+) -> u32 {
+	start_position(creature, simulation);
+	creature.energy = 0.0;
+
+	let start = creature.fitness();
+	let mut checkpoints = vec![start; simulation.exit_rules.len()];
+	let mut exploded = false;
+	let mut frames = simulation.length;
+
+	let centre = creature.centre();
+	let interval = cmp::max(simulation.length / BEHAVIOUR_SAMPLES as u32, 1);
+	let mut trace = Vec::with_capacity(BEHAVIOUR_SAMPLES);
+	let mut contact = 0;
+	let mut height: f32 = 0.0;
+	let mut landed = false;
+
+	for step in 0 .. simulation.length {
+		simulation_step(step, creature, simulation);
+
+		if simulation.environment == Environment::Land {
+			contact += creature.nodes.iter()
+				.filter(|node| node.y >= simulation.floor(node.x))
+				.count();
+
+			let clearance = creature.clearance(simulation);
+			landed = landed || clearance <= 0.0;
+			if landed {
+				height = height.max(clearance);
+			}
+		}
+
+		if (step + 1) % interval == 0 && trace.len() < BEHAVIOUR_SAMPLES - 1 {
+			let (x, y) = creature.centre();
+			trace.push((x - centre.0, y - centre.1));
+		}
+
+		let exit = (0 .. simulation.exit_rules.len()).find(|&idx| {
+			simulation.exit_rules[idx]
+				.triggered(step, creature, &mut checkpoints[idx])
+		});
+
+		if let Some(idx) = exit {
+			if let ExitRule::Exploded(_) = simulation.exit_rules[idx] {
+				exploded = true;
+			}
+			frames = step + 1;
+			break;
+		}
+	}
+
+	// The trace always ends where the creature finished, and one that stopped
+	// early stays there for the rest of its trace
+	let (x, y) = creature.centre();
+	while trace.len() < BEHAVIOUR_SAMPLES {
+		trace.push((x - centre.0, y - centre.1));
+	}
+
+	creature.behaviour = Behaviour {
+		trace: trace,
+		ground_contact:
+			contact as f32 / (frames as usize * creature.nodes.len()) as f32,
+		height: height
+	};
+
+	if exploded {
+		creature.fitness = start;
+	} else {
+		creature.calculate_fitness();
 	}
-	creature.calculate_fitness();
 	creature.reset_position();
+
+	frames
 }
 
-pub fn simulation_step(timer: u32, creature: &mut Creature) {
+pub fn simulation_step(
+	timer: u32,
+	creature: &mut Creature,
+	simulation: &Simulation
+) {
+	// Creatures with a neural controller need to know what they can sense
+	// before deciding how to move their muscles
+	let sensors = if creature.has_controller() {
+		Some(Sensors::read(creature, simulation))
+	} else {
+		None
+	};
+
 	// Loop through every muscle
 	for idx in 0 .. creature.muscles.len() {
-		let target: f32;
-		let t_ext = creature.muscles[idx].time_extended;
-		let t_con = creature.muscles[idx].time_contracted;
-		let step = timer % (t_ext + t_con);
-
-		if step < t_con {
-			target = creature.muscles[idx].len_max;
-			creature.muscles[idx].contracted = false;
-		} else {
-			target = creature.muscles[idx].len_min;
-			creature.muscles[idx].contracted = true;
+		let mut contraction = muscle_contraction(&creature.muscles[idx], timer);
+
+		if let Some(ref sensors) = sensors {
+			let nodes = creature.muscles[idx].nodes.clone();
+			if let Some(ref mut neuron) = creature.muscles[idx].neuron {
+				let inputs = sensors.inputs(&nodes, neuron, contraction);
+				contraction = neuron.activate(&inputs);
+			}
 		}
 
-		force_muscle(creature, idx, target);
+		let target = lerp(
+			creature.muscles[idx].len_max,
+			creature.muscles[idx].len_min,
+			contraction
+		);
+
+		creature.muscles[idx].contracted = contraction >= 0.5;
+
+		creature.energy += force_muscle(creature, idx, target);
+
+		if simulation.environment == Environment::Water {
+			force_drag(creature, idx);
+		}
 	}
 
 	for node in &mut creature.nodes {
-		force_node(node);
-		wall_collision(node);
+		force_external(node, timer, &simulation.forces);
+		match simulation.environment {
+			Environment::Land => {
+				let floor = simulation.floor(node.x);
+				force_node(node);
+				wall_collision(node, floor);
+			},
+			Environment::Water => force_node_buoyant(node)
+		}
 	}
 }
 
-/// Apply a muscle's force to its two connected nodes' velocity vectors
-pub fn force_muscle(creature: &mut Creature, idx: usize, target: f32) {
-	let distance =
-		creature.nodes[creature.muscles[idx].nodes.0]
-		.distance(&creature.nodes[creature.muscles[idx].nodes.1]);
+/// Puts a creature back where it starts, then lowers it onto the ground and
+/// lets it settle if the simulation asks for either
+pub fn start_position(creature: &mut Creature, simulation: &Simulation) {
+	creature.reset_position();
 
-	let angle =
-		(creature.nodes[creature.muscles[idx].nodes.0].y -
-		 creature.nodes[creature.muscles[idx].nodes.1].y)
-		.atan2(creature.nodes[creature.muscles[idx].nodes.0].x -
-		       creature.nodes[creature.muscles[idx].nodes.1].x);
+	// There's nothing to settle onto in the water
+	if simulation.environment == Environment::Land {
+		if simulation.ground_start {
+			creature.ground_start(simulation);
+		}
+		settle(creature, simulation);
+	}
+}
 
-	let force = (1.0 - (distance / target)).max(-0.4).min(0.4);
+/// Lets a creature come to rest on the floor for the simulation's settling
+/// frames, with every muscle held at its resting length, then stops it moving
+/// so the timed simulation starts from its settled pose
+pub fn settle(creature: &mut Creature, simulation: &Simulation) {
+	for _ in 0 .. simulation.settle_frames {
+		for idx in 0 .. creature.muscles.len() {
+			let target = creature.muscles[idx].len;
+			force_muscle(creature, idx, target);
+		}
 
-	creature.nodes[creature.muscles[idx].nodes.0].vx +=
-		angle.cos() * force * creature.muscles[idx].strength;
-	creature.nodes[creature.muscles[idx].nodes.0].vy +=
-		angle.sin() * force * creature.muscles[idx].strength;
+		for node in &mut creature.nodes {
+			let floor = simulation.floor(node.x);
+			force_node(node);
+			wall_collision(node, floor);
+		}
+	}
 
-	creature.nodes[creature.muscles[idx].nodes.1].vx -=
-		angle.cos() * force * creature.muscles[idx].strength;
-	creature.nodes[creature.muscles[idx].nodes.1].vy -=
-		angle.sin() * force * creature.muscles[idx].strength;
+	for node in &mut creature.nodes {
+		node.vx = 0.0;
+		node.vy = 0.0;
+	}
+}
+
+/// Returns how far through contracting a muscle should be at a given frame,
+/// where 0.0 is fully extended and 1.0 is fully contracted
+pub fn muscle_contraction(muscle: &Muscle, timer: u32) -> f32 {
+	contraction(
+		muscle.waveform,
+		muscle.time_extended,
+		muscle.time_contracted,
+		muscle.phase,
+		timer
+	)
+}
+
+/// Returns how far through contracting a muscle with the given timings
+/// should be at a given frame
+pub fn contraction(
+	waveform: Waveform,
+	t_ext: u32,
+	t_con: u32,
+	phase: u32,
+	timer: u32
+) -> f32 {
+	let step = (timer + phase) % (t_ext + t_con);
+
+	// Position within the cycle from 0 to 1, where the first half is spent
+	// extended and the second half contracted, however long each one lasts
+	let cycle = if step < t_con {
+		(step as f32 / t_con as f32) * 0.5
+	} else {
+		0.5 + ((step - t_con) as f32 / t_ext as f32) * 0.5
+	};
+
+	// Smooth waveforms peak in the middle of each half of the cycle
+	let offset = (cycle + 0.75) % 1.0;
+
+	match waveform {
+		Waveform::Square => if cycle < 0.5 { 0.0 } else { 1.0 },
+		Waveform::Sine => (1.0 - (offset * PI * 2.0).cos()) * 0.5,
+		Waveform::Triangle => 1.0 - (1.0 - (offset * 2.0)).abs()
+	}
+}
+
+/// Apply a muscle's force to its two connected nodes' velocity vectors,
+/// returning how much force the muscle pushed or pulled with. The vector
+/// between the two nodes is normalised to give the direction of the force,
+/// the same way a `Batch` does, so both give the same results.
+pub fn force_muscle(creature: &mut Creature, idx: usize, target: f32) -> f32 {
+	let (a, b) = (creature.muscles[idx].nodes.0, creature.muscles[idx].nodes.1);
+
+	let dx = creature.nodes[a].x - creature.nodes[b].x;
+	let dy = creature.nodes[a].y - creature.nodes[b].y;
+	let distance = (dx * dx + dy * dy).sqrt();
+
+	// Two nodes in the same place push along the X axis, the same as the
+	// angle given by atan2(0, 0)
+	let (nx, ny) = if distance > 0.0 {
+		(dx / distance, dy / distance)
+	} else {
+		(1.0, 0.0)
+	};
+
+	let force = (1.0 - (distance / target)).max(-0.4).min(0.4) *
+	            creature.muscles[idx].strength;
+
+	creature.nodes[a].vx += nx * force;
+	creature.nodes[a].vy += ny * force;
+	creature.nodes[b].vx -= nx * force;
+	creature.nodes[b].vy -= ny * force;
+
+	force.abs()
+}
+
+/// Apply every external force field to the specified node's velocity vector
+pub fn force_external(node: &mut Node, timer: u32, forces: &[ForceField]) {
+	for field in forces {
+		let (x, y) = field.force(timer, node);
+		node.vx += x;
+		node.vy += y;
+	}
 }
 
 /// Apply the specified node's velocity vector to its position, including
@@ -83,15 +708,55 @@ pub fn force_node(node: &mut Node) {
 	node.y += node.vy;
 }
 
-/// Check to see if a node is colliding with the floor, and if so prevent it
-/// from going through, and applying friction to the node.
-pub fn wall_collision(node: &mut Node) {
+/// Apply the specified node's velocity vector to its position, without any
+/// gravity as the node is floating in water
+pub fn force_node_buoyant(node: &mut Node) {
+	node.vy *= RESISTANCE;
+	node.vx *= RESISTANCE;
+
+	node.x += node.vx;
+	node.y += node.vy;
+}
+
+/// Apply the water's drag to a muscle's two connected nodes. The muscle's
+/// velocity is split into movement across it and along it, and each is slowed
+/// by a different amount depending on how long the muscle is.
+pub fn force_drag(creature: &mut Creature, idx: usize) {
+	let (a, b) = (creature.muscles[idx].nodes.0, creature.muscles[idx].nodes.1);
+
+	let dx = creature.nodes[a].x - creature.nodes[b].x;
+	let dy = creature.nodes[a].y - creature.nodes[b].y;
+	let length = (dx * dx + dy * dy).sqrt();
+	if length <= 0.0 { return; }
+
+	// Direction along the muscle, and the direction across it
+	let (tx, ty) = (dx / length, dy / length);
+	let (nx, ny) = (-ty, tx);
+
+	let vx = (creature.nodes[a].vx + creature.nodes[b].vx) * 0.5;
+	let vy = (creature.nodes[a].vy + creature.nodes[b].vy) * 0.5;
+
+	let normal = -(vx * nx + vy * ny) * (DRAG_NORMAL * length).min(DRAG_MAX);
+	let tangent = -(vx * tx + vy * ty) * (DRAG_TANGENT * length).min(DRAG_MAX);
+
+	let fx = nx * normal + tx * tangent;
+	let fy = ny * normal + ty * tangent;
+
+	for &node in &[a, b] {
+		creature.nodes[node].vx += fx;
+		creature.nodes[node].vy += fy;
+	}
+}
+
+/// Check to see if a node is colliding with the floor at the given height,
+/// and if so prevent it from going through, and applying friction to the node.
+pub fn wall_collision(node: &mut Node, floor: f32) {
 	let y = node.y;
 	// Y position in the world is 0, so anything above that means it's
 	// colliding
-	if y >= creature::BOUNDS_NODE_Y.end {
+	if y >= floor {
 		// So the node doesn't get actually drawn in the ground
-		node.y = creature::BOUNDS_NODE_Y.end;
+		node.y = floor;
 		// Reset the velocity of Y as we're against the ground
 		node.vy = 0.0;
 		node.x -= node.vx * node.friction;
@@ -119,8 +784,10 @@ pub fn clamp(value: f32, range: Range<f32>) -> f32 {
 
 #[cfg(test)]
 mod tests {
-	use physics;
-	use creature::{self, Creature, Node, Muscle, NodePair};
+	use rand;
+	use physics::{self, ForceField};
+	use controller::{Neuron, NEURON_INPUTS};
+	use creature::{self, Creature, Node, Muscle, NodePair, Waveform};
 
 	/// Create a simple creature that is used for most of the physics tests
 	fn simple_creature(start_x: f32, start_y: f32) -> Creature {
@@ -150,11 +817,16 @@ mod tests {
 		creature.add_muscle(Muscle {
 			nodes: NodePair(0, 1),
 			strength: 1.0,
-			len_min: start_x * creature::BOUNDS_MUSCLE_LENGTH.start,
+			len_min: start_x * creature::DEFAULT_MUSCLE_CONTRACTION,
 			len: start_x,
-			len_max: start_x * creature::BOUNDS_MUSCLE_LENGTH.end,
+			len_max: start_x * creature::DEFAULT_MUSCLE_EXTENSION,
+			contraction: creature::DEFAULT_MUSCLE_CONTRACTION,
+			extension: creature::DEFAULT_MUSCLE_EXTENSION,
 			time_extended: 60,
 			time_contracted: 60,
+			phase: 0,
+			waveform: Waveform::Square,
+			neuron: None,
 			contracted: false
 		});
 
@@ -208,6 +880,39 @@ mod tests {
 		}
 	}
 
+	/// Make sure each type of force field pushes with the expected force, and
+	/// that every field is added to a node's velocity
+	#[test]
+	fn force_external() {
+		let creature = simple_creature(64.0, 0.0);
+		let inside = &creature.nodes[0];
+		let outside = &creature.nodes[1];
+
+		let uniform = ForceField::Uniform { x: -0.5, y: 0.1 };
+		assert_eq!(uniform.force(0, inside), (-0.5, 0.1));
+		assert_eq!(uniform.force(500, outside), (-0.5, 0.1));
+
+		// Gusts are calm at the start of each period and strongest halfway
+		let gust = ForceField::Gust { x: 1.0, y: 0.0, period: 100 };
+		assert_approx_eq!(gust.force(0, inside).0, 0.0);
+		assert_approx_eq!(gust.force(50, inside).0, 1.0);
+		assert_approx_eq!(gust.force(100, inside).0, 0.0);
+
+		let zone = ForceField::Zone {
+			area_x: -32.0 .. 32.0,
+			area_y: -32.0 .. 32.0,
+			x: 0.0,
+			y: -1.0
+		};
+		assert_eq!(zone.force(0, inside), (0.0, -1.0));
+		assert_eq!(zone.force(0, outside), (0.0, 0.0));
+
+		let mut node = inside.clone();
+		physics::force_external(&mut node, 0, &[uniform, zone]);
+		assert_approx_eq!(node.vx, -0.5);
+		assert_approx_eq!(node.vy, -0.9);
+	}
+
 	/// Create a creature where one node is almost touching the ground, and
 	/// make sure it stops at the ground.
 	#[test]
@@ -220,12 +925,394 @@ mod tests {
 		for _ in 0 .. 60 {
 			for node in &mut creature.nodes {
 				physics::force_node(node);
-				physics::wall_collision(node);
+				physics::wall_collision(node, creature::BOUNDS_NODE_Y.end);
 				assert!(node.y <= creature::BOUNDS_NODE_Y.end);
 			}
 		}
 	}
 
+	/// Make sure water pushes back harder against a muscle moving across its
+	/// length than one moving along it
+	#[test]
+	fn force_drag() {
+		let mut across = simple_creature(64.0, 0.0);
+		let mut along = simple_creature(64.0, 0.0);
+
+		for node in &mut across.nodes { node.vy = 1.0; }
+		for node in &mut along.nodes { node.vx = 1.0; }
+
+		physics::force_drag(&mut across, 0);
+		physics::force_drag(&mut along, 0);
+
+		for idx in 0 .. 2 {
+			assert_approx_eq!(across.nodes[idx].vx, 0.0);
+			assert_approx_eq!(along.nodes[idx].vy, 0.0);
+			assert!(across.nodes[idx].vy > 0.0);
+			assert!(along.nodes[idx].vx > 0.0);
+			assert!(across.nodes[idx].vy < along.nodes[idx].vx);
+		}
+	}
+
+	/// Make sure creatures in water float without falling or hitting a floor
+	#[test]
+	fn water_buoyant() {
+		let mut creature = simple_creature(64.0, 0.0);
+		let simulation = physics::Simulation::water();
+
+		for timer in 0 .. 100 {
+			physics::simulation_step(timer, &mut creature, &simulation);
+		}
+
+		// Allow for rounding errors from the muscle's angle
+		for node in &creature.nodes {
+			assert!(node.y.abs() < 0.01);
+		}
+	}
+
+	/// Make sure a square wave with no phase offset behaves like the original
+	/// on/off timer, and that the phase offset shifts it along
+	#[test]
+	fn muscle_contraction_square() {
+		let mut creature = simple_creature(64.0, 0.0);
+
+		assert_approx_eq!(
+			physics::muscle_contraction(&creature.muscles[0], 0), 0.0
+		);
+		assert_approx_eq!(
+			physics::muscle_contraction(&creature.muscles[0], 59), 0.0
+		);
+		assert_approx_eq!(
+			physics::muscle_contraction(&creature.muscles[0], 60), 1.0
+		);
+		assert_approx_eq!(
+			physics::muscle_contraction(&creature.muscles[0], 120), 0.0
+		);
+
+		creature.muscles[0].phase = 60;
+		assert_approx_eq!(
+			physics::muscle_contraction(&creature.muscles[0], 0), 1.0
+		);
+	}
+
+	/// Make sure the smooth waveforms stay between fully extended and fully
+	/// contracted, reaching both in the middle of each half of the cycle
+	#[test]
+	fn muscle_contraction_smooth() {
+		let mut creature = simple_creature(64.0, 0.0);
+
+		for waveform in &[Waveform::Sine, Waveform::Triangle] {
+			creature.muscles[0].waveform = *waveform;
+
+			for timer in 0 .. 120 {
+				let contraction =
+					physics::muscle_contraction(&creature.muscles[0], timer);
+				assert!(contraction >= 0.0 && contraction <= 1.0);
+			}
+
+			assert_approx_eq!(
+				physics::muscle_contraction(&creature.muscles[0], 30), 0.0
+			);
+			assert_approx_eq!(
+				physics::muscle_contraction(&creature.muscles[0], 90), 1.0
+			);
+		}
+	}
+
+	/// Simulate a creature driven by a random neural controller, making sure
+	/// it never flies apart and its neurons are reset afterwards
+	#[test]
+	fn controller_step() {
+		let mut rng = rand::thread_rng();
+		let mut creature = simple_creature(64.0, 0.0);
+		creature.controller_add(&mut rng);
+		let simulation = physics::Simulation::default();
+
+		for timer in 0 .. physics::SIM_LENGTH {
+			physics::simulation_step(timer, &mut creature, &simulation);
+			for node in &creature.nodes {
+				assert!(node.x.is_finite() && node.y.is_finite());
+			}
+		}
+
+		creature.reset_position();
+		match creature.muscles[0].neuron {
+			Some(ref neuron) => assert_eq!(neuron.output, 0.0),
+			None => assert!(false)
+		}
+	}
+
+	/// Drive a muscle with a neuron which only reacts to its nodes touching
+	/// the ground, making sure it extends in the air and contracts once the
+	/// creature lands
+	#[test]
+	fn controller_contact() {
+		let mut creature = simple_creature(64.0, 0.0);
+		let mut weights = [0.0; NEURON_INPUTS];
+		weights[0] = 2.0;
+		weights[1] = 2.0;
+		creature.muscles[0].neuron = Some(Neuron {
+			weights: weights,
+			bias: -2.0,
+			output: 0.0
+		});
+		let simulation = physics::Simulation::default();
+		let output = |creature: &Creature| {
+			creature.muscles[0].neuron.as_ref().unwrap().output
+		};
+
+		physics::simulation_step(0, &mut creature, &simulation);
+		let airborne = output(&creature);
+		assert!(airborne < -0.9);
+		assert!(!creature.muscles[0].contracted);
+
+		for node in &mut creature.nodes {
+			node.y = creature::BOUNDS_NODE_Y.end;
+			node.vy = 0.0;
+		}
+		physics::simulation_step(1, &mut creature, &simulation);
+		let grounded = output(&creature);
+		assert!(grounded > 0.9);
+		assert!(creature.muscles[0].contracted);
+	}
+
+	/// Let a creature in the air settle, making sure it lands on the ground
+	/// and is no longer moving when the timed simulation would begin
+	#[test]
+	fn settle() {
+		let mut creature = simple_creature(64.0, 0.0);
+		let simulation = physics::Simulation {
+			settle_frames: 120,
+			.. physics::Simulation::default()
+		};
+
+		physics::settle(&mut creature, &simulation);
+
+		for node in &creature.nodes {
+			assert_approx_eq!(node.y, creature::BOUNDS_NODE_Y.end);
+			assert_eq!(node.vx, 0.0);
+			assert_eq!(node.vy, 0.0);
+		}
+	}
+
+	/// Make sure creatures are left in the air by default, and only moved
+	/// onto the ground when the simulation asks for it
+	#[test]
+	fn start_position() {
+		let mut creature = simple_creature(64.0, 0.0);
+		let mut simulation = physics::Simulation::default();
+
+		physics::start_position(&mut creature, &simulation);
+		for node in &creature.nodes {
+			assert_eq!(node.y, 0.0);
+		}
+
+		simulation.ground_start = true;
+		physics::start_position(&mut creature, &simulation);
+		for node in &creature.nodes {
+			assert_approx_eq!(node.y, creature::BOUNDS_NODE_Y.end);
+			assert_eq!(node.start_y, 0.0);
+		}
+
+		// On a slope only the node nearest the floor touches it, and the
+		// other stays above it
+		simulation.slope = 0.5;
+		physics::start_position(&mut creature, &simulation);
+		let floor_0 = simulation.floor(creature.nodes[0].x);
+		let floor_1 = simulation.floor(creature.nodes[1].x);
+		assert_approx_eq!(creature.nodes[1].y, floor_1);
+		assert!(creature.nodes[0].y < floor_0);
+
+		// Settling lands the creature on the sloped floor, not a flat one
+		simulation.settle_frames = 120;
+		physics::start_position(&mut creature, &simulation);
+		// Nodes sliding along the floor on the last frame can end up a
+		// fraction below where the floor is under them now
+		for node in &creature.nodes {
+			assert!(node.y <= simulation.floor(node.x) + 0.5);
+		}
+		assert!(creature.nodes[0].y > creature::BOUNDS_NODE_Y.end);
+	}
+
+	/// Simulate a creature that cannot move, making sure it stops early and
+	/// keeps the same fitness as running the whole simulation
+	#[test]
+	fn early_exit_motionless() {
+		let mut creature = simple_creature(64.0, creature::BOUNDS_NODE_Y.end);
+		creature.muscles[0].strength = 0.0;
+
+		let mut full = creature.clone();
+		physics::full_simulation_creature(
+			&mut full,
+			&physics::Simulation::default()
+		);
+
+		let frames = physics::full_simulation_creature(
+			&mut creature,
+			&physics::Simulation::early_exit()
+		);
+
+		assert!(frames < physics::SIM_LENGTH);
+		assert_approx_eq!(creature.fitness, full.fitness);
+	}
+
+	/// Check a motionless rule with no frames between checks runs every frame
+	/// instead of dividing by zero
+	#[test]
+	fn early_exit_motionless_zero_frames() {
+		let creature = simple_creature(64.0, creature::BOUNDS_NODE_Y.end);
+		let rule = physics::ExitRule::Motionless { frames: 0, distance: 2.0 };
+		let mut checkpoint = creature.fitness();
+
+		assert!(rule.triggered(0, &creature, &mut checkpoint));
+	}
+
+	/// Simulate a creature whose muscle is already stretched too far, making
+	/// sure it stops on the first frame and keeps its starting fitness
+	#[test]
+	fn early_exit_exploded() {
+		let mut creature = simple_creature(64.0, 0.0);
+		creature.muscles[0].len_max = 1.0;
+		let start = creature.fitness();
+
+		let frames = physics::full_simulation_creature(
+			&mut creature,
+			&physics::Simulation::early_exit()
+		);
+
+		assert_eq!(frames, 1);
+		assert_approx_eq!(creature.fitness, start);
+	}
+
+	/// Make sure nodes land on a sloped floor at the right height
+	#[test]
+	fn floor_slope() {
+		let mut simulation = physics::Simulation::default();
+		assert_eq!(simulation.floor(0.0), creature::BOUNDS_NODE_Y.end);
+
+		simulation.slope = 0.5;
+		assert_approx_eq!(
+			simulation.floor(creature::BOUNDS_NODE_X.end),
+			creature::BOUNDS_NODE_Y.end - 64.0
+		);
+
+		let mut creature = simple_creature(creature::BOUNDS_NODE_X.end, 0.0);
+		for _ in 0 .. 200 {
+			for node in &mut creature.nodes {
+				let floor = simulation.floor(node.x);
+				physics::force_node(node);
+				physics::wall_collision(node, floor);
+				assert!(node.y <= floor);
+			}
+		}
+	}
+
+	/// Make sure simulating a creature several times records the spread of
+	/// its fitness values, and that without any changes every simulation
+	/// gives the same result
+	#[test]
+	fn perturbed_simulation() {
+		let mut rng = rand::thread_rng();
+		let mut creature = Creature::new(&mut rng);
+		let mut simulation = physics::Simulation::robust(5);
+
+		physics::full_simulation_creature(&mut creature, &simulation);
+		let stats = creature.fitness_stats.expect("Missing fitness stats");
+		assert_approx_eq!(creature.fitness, stats.mean);
+		assert!(stats.min <= stats.mean);
+		assert!(stats.variance >= 0.0);
+
+		let mut single = creature.clone();
+		physics::full_simulation_creature(
+			&mut single,
+			&physics::Simulation::default()
+		);
+		assert!(single.fitness_stats.is_none());
+
+		simulation.perturbation = Some(physics::Perturbation {
+			evaluations: 3,
+			jitter: 0.0,
+			friction: 0.0,
+			slope: 0.0
+		});
+		physics::full_simulation_creature(&mut creature, &simulation);
+		// Averaging the same fitness several times can round its last digit
+		let stats = creature.fitness_stats.expect("Missing fitness stats");
+		let tolerance = single.fitness.abs().max(1.0) * 1e-5;
+		assert_approx_eq!(stats.mean, single.fitness, tolerance);
+		assert_approx_eq!(stats.min, single.fitness, tolerance);
+		assert_approx_eq!(stats.variance, 0.0, tolerance);
+	}
+
+	/// Make sure the parsimony penalty is taken away from a creature's
+	/// fitness, once for every node and muscle
+	#[test]
+	fn parsimony() {
+		let mut rng = rand::thread_rng();
+		let creature = Creature::new(&mut rng);
+		let mut simulation = physics::Simulation::default();
+
+		let mut plain = creature.clone();
+		physics::full_simulation_creature(&mut plain, &simulation);
+
+		simulation.parsimony = Some(physics::Parsimony {
+			node: 2.0,
+			muscle: 1.0
+		});
+		let mut penalised = creature.clone();
+		physics::full_simulation_creature(&mut penalised, &simulation);
+
+		assert_approx_eq!(
+			penalised.fitness,
+			plain.fitness -
+			(creature.nodes.len() as f32 * 2.0) -
+			creature.muscles.len() as f32
+		);
+	}
+
+	/// Make sure a simulation records the creature's behaviour, finishing
+	/// its trace where the creature ended up
+	#[test]
+	fn behaviour() {
+		let mut rng = rand::thread_rng();
+		let mut creature = Creature::new(&mut rng);
+		let start = creature.centre();
+
+		physics::full_simulation_creature(
+			&mut creature,
+			&physics::Simulation::default()
+		);
+
+		let behaviour = &creature.behaviour;
+		assert_eq!(behaviour.trace.len(), physics::BEHAVIOUR_SAMPLES);
+		assert!(
+			behaviour.ground_contact >= 0.0 && behaviour.ground_contact <= 1.0
+		);
+
+		let last = behaviour.trace[physics::BEHAVIOUR_SAMPLES - 1];
+		assert_approx_eq!(
+			start.0 + last.0 - creature::BOUNDS_NODE_X.end / 2.0,
+			creature.fitness,
+			0.01
+		);
+		assert_eq!(behaviour.distance(behaviour), 0.0);
+	}
+
+	/// Drop a creature that can't move from high in the air, making sure the
+	/// fall isn't counted as a jump once it lands
+	#[test]
+	fn behaviour_height() {
+		let mut creature = simple_creature(64.0, 0.0);
+		creature.muscles[0].strength = 0.0;
+
+		physics::full_simulation_creature(
+			&mut creature,
+			&physics::Simulation::default()
+		);
+
+		assert!(creature.behaviour.ground_contact > 0.0);
+		assert_approx_eq!(creature.behaviour.height, 0.0);
+	}
+
 	/// Makes sure linear interpolation function correctly lerps between
 	/// two numbers correctly
 	#[test]
@@ -1,6 +1,7 @@
 use creature::Creature;
-use physics;
+use physics::{self, Simulation};
 use rand::ThreadRng;
+use std::cmp;
 
 #[derive(Clone)]
 pub struct Population {
@@ -35,9 +36,24 @@ impl Population {
 		}
 	}
 
+	/// Gives every creature in the population a neural controller, and
+	/// recalculates their fitness to match
+	pub fn controller_add(&mut self, rng: &mut ThreadRng) {
+		for creature in &mut self.creatures {
+			creature.controller_add(rng);
+		}
+		self.calculate_fitness();
+	}
+
 	/// Runs the physics calculations for every creature in the population
 	pub fn calculate_fitness(&mut self) {
-		physics::full_simulation_population(self);
+		self.simulate(&Simulation::default());
+	}
+
+	/// Runs the physics calculations for every creature in the population
+	/// using the given simulation settings
+	pub fn simulate(&mut self, simulation: &Simulation) {
+		physics::full_simulation_population(self, simulation);
 		self.sort_by_fittest();
 	}
 
@@ -60,15 +76,46 @@ impl Population {
 		total_fitness / self.creatures.len() as f32
 	}
 
+	/// Calculates the average number of nodes and muscles each creature in
+	/// the population has
+	pub fn complexity_average(&self) -> (f32, f32) {
+		let mut nodes = 0;
+		let mut muscles = 0;
+		for creature in &self.creatures {
+			nodes += creature.nodes.len();
+			muscles += creature.muscles.len();
+		}
+
+		let len = self.creatures.len() as f32;
+		(nodes as f32 / len, muscles as f32 / len)
+	}
+
 	/// Sort the population into fitness first
 	pub fn sort_by_fittest(&mut self) {
 		self.creatures.sort_by(|a, b| b.cmp(a));
 	}
+
+	/// Split the population into `count` smaller ones, clamped to between 1
+	/// and the number of creatures. Creatures are dealt out in turn, so every
+	/// part starts with a similar spread of fitness.
+	pub fn deal(&self, count: usize) -> Vec<Population> {
+		let count = cmp::max(cmp::min(count, self.creatures.len()), 1);
+		let mut parts: Vec<Population> = (0 .. count)
+			.map(|_| Population::empty(self.creatures.len() / count + 1))
+			.collect();
+
+		for (idx, creature) in self.creatures.iter().enumerate() {
+			parts[idx % count].creatures.push(creature.clone());
+		}
+
+		parts
+	}
 }
 
 #[cfg(test)]
 mod tests {
 	use population::*;
+	use creature;
 	use rand;
 
 	/// Create a population filled with randomly generated creatures
@@ -123,4 +170,43 @@ mod tests {
 		assert!(average_fitness < fittest.fitness);
 	}
 
+	/// Make sure the average complexity lies within the creature bounds
+	#[test]
+	fn complexity_average() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(100, &mut rng);
+
+		let (nodes, muscles) = population.complexity_average();
+		assert!(nodes >= creature::BOUNDS_NODE_COUNT.start as f32);
+		assert!(nodes <= creature::BOUNDS_NODE_COUNT.end as f32);
+		assert!(muscles >= nodes / 2.0);
+	}
+
+	/// Deal a population out and make sure every creature ends up in exactly
+	/// one part, with the parts as even as possible
+	#[test]
+	fn deal() {
+		let mut rng = rand::thread_rng();
+		let population = Population::new(10, &mut rng);
+
+		let parts = population.deal(4);
+		assert_eq!(parts.len(), 4);
+		let sizes = parts.iter()
+			.map(|part| part.creatures.len())
+			.collect::<Vec<usize>>();
+		assert_eq!(sizes, vec![3, 3, 2, 2]);
+
+		let fitness = |part: &Population| part.creatures.iter()
+			.map(|creature| creature.fitness)
+			.collect::<Vec<f32>>();
+		assert_eq!(fitness(&parts[1]), vec![
+			population.creatures[1].fitness,
+			population.creatures[5].fitness,
+			population.creatures[9].fitness
+		]);
+
+		assert_eq!(population.deal(0).len(), 1);
+		assert_eq!(population.deal(50).len(), 10);
+	}
+
 }
@@ -7,7 +7,7 @@ use cmp6102::optimisationmethods::genetic_algorithm::GeneticAlgorithm;
 use cmp6102::optimisationmethods::simulated_annealing::{
 	self, SimulatedAnnealing
 };
-use cmp6102::optimisationmethods::hill_climbing::HillClimbing;
+use cmp6102::optimisationmethods::hill_climbing::{HillClimbing, Strategy};
 
 /// Initialise the data required for the integration tests
 pub fn init(pop_size: usize) -> Population{
@@ -33,7 +33,7 @@ fn three_opt_methods() {
 	// final OM, as we won't need it anymore.
 	om.push(GeneticAlgorithm::new(pop.clone(), print_data));
 	om.push(SimulatedAnnealing::new(pop.clone(), print_data));
-	om.push(HillClimbing::new(pop, print_data));
+	om.push(HillClimbing::new(pop, print_data, Strategy::SteepestAscent));
 
 	// Run the specified number of generations on each OM
 	for idx in 0 .. om.len() {